@@ -0,0 +1,432 @@
+use crate::{
+	ByteLen, DecodeError, DecodedChar, DecodedCharIndices, Endianness, Position, PositionTracker,
+	Positions, Utf16BytesDecoded, Utf32BytesDecoded, Utf8BytesDecoded,
+};
+
+/// Unicode encoding form.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum UnicodeEncoding {
+	/// UTF-8.
+	Utf8,
+
+	/// UTF-16, little endian.
+	Utf16Le,
+
+	/// UTF-16, big endian.
+	Utf16Be,
+
+	/// UTF-32, little endian.
+	Utf32Le,
+
+	/// UTF-32, big endian.
+	Utf32Be,
+}
+
+impl UnicodeEncoding {
+	/// Returns the byte order mark of this encoding.
+	#[inline(always)]
+	pub fn bom(&self) -> &'static [u8] {
+		match self {
+			Self::Utf8 => &[0xef, 0xbb, 0xbf],
+			Self::Utf16Le => &[0xff, 0xfe],
+			Self::Utf16Be => &[0xfe, 0xff],
+			Self::Utf32Le => &[0xff, 0xfe, 0x00, 0x00],
+			Self::Utf32Be => &[0x00, 0x00, 0xfe, 0xff],
+		}
+	}
+
+	/// Returns the byte size of a code unit in this encoding.
+	#[inline(always)]
+	pub fn code_unit_size(&self) -> usize {
+		match self {
+			Self::Utf8 => 1,
+			Self::Utf16Le | Self::Utf16Be => 2,
+			Self::Utf32Le | Self::Utf32Be => 4,
+		}
+	}
+
+	/// Returns the name of the encoding.
+	#[inline(always)]
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Utf8 => "UTF-8",
+			Self::Utf16Le => "UTF-16LE",
+			Self::Utf16Be => "UTF-16BE",
+			Self::Utf32Le => "UTF-32LE",
+			Self::Utf32Be => "UTF-32BE",
+		}
+	}
+
+	/// Finds the encoding whose byte order mark starts the given bytes.
+	///
+	/// A UTF-32LE byte order mark is preferred over a UTF-16LE byte order
+	/// mark followed by a `U+0000` character.
+	pub fn sniff_bom(bytes: &[u8]) -> Option<Self> {
+		[
+			Self::Utf8,
+			Self::Utf32Le,
+			Self::Utf32Be,
+			Self::Utf16Le,
+			Self::Utf16Be,
+		]
+		.into_iter()
+		.find(|e| bytes.starts_with(e.bom()))
+	}
+}
+
+/// Byte order mark handling.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub enum BomHandling {
+	/// The byte order mark is skipped: the first character is the one
+	/// following it, with an offset equal to the length of the byte order
+	/// mark.
+	#[default]
+	Skip,
+
+	/// The byte order mark is emitted as a `U+FEFF` character, whose length
+	/// is the byte length of the byte order mark.
+	Emit,
+}
+
+/// Decoder for Unicode encoded bytes, in any of the Unicode encoding forms.
+#[derive(Clone, Debug)]
+pub enum UnicodeDecoded<B> {
+	/// UTF-8 decoder.
+	Utf8(Utf8BytesDecoded<B>),
+
+	/// UTF-16 decoder.
+	Utf16(Utf16BytesDecoded<B>),
+
+	/// UTF-32 decoder.
+	Utf32(Utf32BytesDecoded<B>),
+}
+
+impl<B> UnicodeDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: UnicodeEncoding) -> Self {
+		Self::with_start(bytes, encoding, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	pub fn with_start(bytes: B, encoding: UnicodeEncoding, offset: usize) -> Self {
+		match encoding {
+			UnicodeEncoding::Utf8 => Self::Utf8(Utf8BytesDecoded::with_start(bytes, offset)),
+			UnicodeEncoding::Utf16Le => Self::Utf16(Utf16BytesDecoded::with_start(
+				bytes,
+				Endianness::Little,
+				offset,
+			)),
+			UnicodeEncoding::Utf16Be => Self::Utf16(Utf16BytesDecoded::with_start(
+				bytes,
+				Endianness::Big,
+				offset,
+			)),
+			UnicodeEncoding::Utf32Le => Self::Utf32(Utf32BytesDecoded::with_start(
+				bytes,
+				Endianness::Little,
+				offset,
+			)),
+			UnicodeEncoding::Utf32Be => Self::Utf32(Utf32BytesDecoded::with_start(
+				bytes,
+				Endianness::Big,
+				offset,
+			)),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::Utf8(d) => d.offset(),
+			Self::Utf16(d) => d.offset(),
+			Self::Utf32(d) => d.offset(),
+		}
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for UnicodeDecoded<B> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		match self {
+			Self::Utf8(d) => d.next().map(|r| r.map_err(Into::into)),
+			Self::Utf16(d) => d.next().map(|r| r.map_err(Into::into)),
+			Self::Utf32(d) => d.next().map(|r| r.map_err(Into::into)),
+		}
+	}
+}
+
+/// Bytes iterator, starting with the bytes read while looking for a byte
+/// order mark.
+#[derive(Clone, Debug)]
+pub struct Sniffed<B> {
+	/// Bytes read ahead.
+	prefix: [u8; 4],
+
+	/// Index of the next byte in `prefix`.
+	start: usize,
+
+	/// Number of bytes in `prefix`.
+	end: usize,
+
+	/// Remaining bytes.
+	rest: B,
+}
+
+impl<B: Iterator<Item = u8>> Iterator for Sniffed<B> {
+	type Item = u8;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<u8> {
+		if self.start < self.end {
+			self.start += 1;
+			Some(self.prefix[self.start - 1])
+		} else {
+			self.rest.next()
+		}
+	}
+}
+
+/// Decoder selecting the Unicode encoding form of its input from its byte
+/// order mark.
+///
+/// Offsets of the decoded characters (and errors) always match positions in
+/// the original input, including the byte order mark, whether it is skipped
+/// or emitted.
+#[derive(Clone, Debug)]
+pub struct BomDecoded<B> {
+	/// Selected encoding.
+	encoding: UnicodeEncoding,
+
+	/// Whether or not a byte order mark was found.
+	has_bom: bool,
+
+	/// Byte order mark character to emit, if any.
+	pending: Option<DecodedChar>,
+
+	/// Decoder.
+	inner: UnicodeDecoded<Sniffed<B>>,
+}
+
+impl<B: Iterator<Item = u8>> BomDecoded<B> {
+	/// Creates a new decoder, inspecting the first bytes of the input for a
+	/// byte order mark.
+	///
+	/// The input is decoded as UTF-8 if no byte order mark is found.
+	#[inline(always)]
+	pub fn new(bytes: B, handling: BomHandling) -> Self {
+		Self::with_fallback(bytes, handling, UnicodeEncoding::Utf8)
+	}
+
+	/// Creates a new decoder, inspecting the first bytes of the input for a
+	/// byte order mark.
+	///
+	/// The input is decoded with the `fallback` encoding if no byte order mark
+	/// is found.
+	pub fn with_fallback(mut bytes: B, handling: BomHandling, fallback: UnicodeEncoding) -> Self {
+		let mut prefix = [0; 4];
+		let mut end = 0;
+		while end < 4 {
+			match bytes.next() {
+				Some(b) => {
+					prefix[end] = b;
+					end += 1
+				}
+				None => break,
+			}
+		}
+
+		let bom = UnicodeEncoding::sniff_bom(&prefix[..end]);
+		let encoding = bom.unwrap_or(fallback);
+		let start = bom.map(|e| e.bom().len()).unwrap_or(0);
+		let pending = match handling {
+			BomHandling::Skip => None,
+			BomHandling::Emit => bom.map(|_| DecodedChar::new('\u{feff}', ByteLen(start))),
+		};
+
+		Self {
+			encoding,
+			has_bom: bom.is_some(),
+			pending,
+			inner: UnicodeDecoded::with_start(
+				Sniffed {
+					prefix,
+					start,
+					end,
+					rest: bytes,
+				},
+				encoding,
+				start,
+			),
+		}
+	}
+
+	/// Returns the selected encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> UnicodeEncoding {
+		self.encoding
+	}
+
+	/// Checks if the input starts with a byte order mark.
+	#[inline(always)]
+	pub fn has_bom(&self) -> bool {
+		self.has_bom
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		if self.pending.is_some() {
+			0
+		} else {
+			self.inner.offset()
+		}
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// byte offset in the encoded source file, starting after a skipped byte
+	/// order mark.
+	///
+	/// This shadows `DecodedIterator::with_offsets`, which would start at
+	/// offset 0 after a skipped byte order mark while error offsets do not.
+	#[inline(always)]
+	pub fn with_offsets(self) -> DecodedCharIndices<Self> {
+		let offset = self.offset();
+		DecodedCharIndices::with_start(self, offset)
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// position in the encoded source file, starting after a skipped byte
+	/// order mark.
+	///
+	/// This shadows `DecodedIterator::with_positions`, which would start at
+	/// offset 0 after a skipped byte order mark while error offsets do not.
+	#[inline(always)]
+	pub fn with_positions(self) -> Positions<Self> {
+		let tracker = PositionTracker::starting_at(Position {
+			byte: self.offset(),
+			..Position::default()
+		});
+		Positions::new(self, tracker)
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for BomDecoded<B> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		match self.pending.take() {
+			Some(c) => Some(Ok(c)),
+			None => self.inner.next(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedBytes;
+
+	/// Decodes the given bytes, returning the encoding and each character
+	/// with its offset.
+	fn decode(bytes: &[u8], handling: BomHandling) -> (UnicodeEncoding, Vec<(usize, char)>) {
+		let decoder = bytes.decoded_chars_with_bom(handling);
+		let encoding = decoder.encoding();
+		let chars = decoder
+			.with_offsets()
+			.map(|item| {
+				let (offset, c) = item.unwrap();
+				(offset, c.chr())
+			})
+			.collect();
+		(encoding, chars)
+	}
+
+	#[test]
+	fn skip() {
+		use UnicodeEncoding::*;
+		let cases: [(&[u8], _, &[_]); 6] = [
+			(b"\xef\xbb\xbfa", Utf8, &[(3, 'a')]),
+			(b"\xff\xfea\x00", Utf16Le, &[(2, 'a')]),
+			(b"\xfe\xff\x00a", Utf16Be, &[(2, 'a')]),
+			(b"\xff\xfe\x00\x00a\x00\x00\x00", Utf32Le, &[(4, 'a')]),
+			(b"\x00\x00\xfe\xff\x00\x00\x00a", Utf32Be, &[(4, 'a')]),
+			(b"ab", Utf8, &[(0, 'a'), (1, 'b')]),
+		];
+
+		for (bytes, encoding, chars) in cases {
+			assert_eq!(decode(bytes, BomHandling::Skip), (encoding, chars.to_vec()));
+		}
+	}
+
+	#[test]
+	fn emit() {
+		assert_eq!(
+			decode(b"\xff\xfea\x00", BomHandling::Emit),
+			(UnicodeEncoding::Utf16Le, vec![(0, '\u{feff}'), (2, 'a')])
+		);
+
+		let lens: Vec<_> = b"\xef\xbb\xbfa"
+			.decoded_chars_with_bom(BomHandling::Emit)
+			.map(|c| c.unwrap().len().0)
+			.collect();
+		assert_eq!(lens, [3, 1]);
+	}
+
+	#[test]
+	fn positions() {
+		let positions: Vec<_> = b"\xef\xbb\xbfa\nb"
+			.decoded_chars_with_bom(BomHandling::Skip)
+			.with_positions()
+			.map(|item| {
+				let (p, _) = item.unwrap();
+				(p.byte, p.line, p.column)
+			})
+			.collect();
+		assert_eq!(positions, [(3, 0, 0), (4, 0, 1), (5, 1, 0)]);
+	}
+
+	#[test]
+	fn offsets_after_invalid_bytes() {
+		let offsets: Vec<_> = b"\xef\xbb\xbfa\xffb"
+			.decoded_chars_with_bom(BomHandling::Skip)
+			.with_offsets()
+			.map(|item| {
+				item.map(|(offset, c)| (offset, c.chr()))
+					.map_err(|e| e.offset())
+			})
+			.collect();
+		assert_eq!(offsets, [Ok((3, 'a')), Err(4), Ok((5, 'b'))]);
+	}
+
+	#[test]
+	fn fallback() {
+		let decoder = BomDecoded::with_fallback(
+			b"a\x00".iter().copied(),
+			BomHandling::Skip,
+			UnicodeEncoding::Utf16Le,
+		);
+		assert!(!decoder.has_bom());
+		let chars: Vec<_> = decoder.map(|c| c.unwrap().chr()).collect();
+		assert_eq!(chars, ['a']);
+	}
+
+	#[test]
+	fn sniff() {
+		assert_eq!(
+			UnicodeEncoding::sniff_bom(b"\xff\xfe\x00\x00"),
+			Some(UnicodeEncoding::Utf32Le)
+		);
+		assert_eq!(
+			UnicodeEncoding::sniff_bom(b"\xff\xfe\x00"),
+			Some(UnicodeEncoding::Utf16Le)
+		);
+		assert_eq!(UnicodeEncoding::sniff_bom(b"\xef\xbb"), None);
+	}
+}
@@ -0,0 +1,678 @@
+use crate::{ByteLen, DecodedChar};
+use encoding_index_japanese::{jis0208, jis0212};
+use encoding_index_korean::euc_kr;
+use encoding_index_simpchinese::{gb18030, gb18030_ranges};
+use encoding_index_tradchinese::big5;
+use std::fmt;
+
+/// Multi-byte CJK legacy encoding.
+///
+/// All these encodings are ASCII-compatible and decoded following the
+/// algorithms and index tables of the WHATWG Encoding Standard.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum CjkEncoding {
+	/// Shift_JIS (Japanese), including the Windows-31J extensions.
+	ShiftJis,
+
+	/// EUC-JP (Japanese), including the JIS X 0212 supplement.
+	EucJp,
+
+	/// EUC-KR (Korean), including the Windows-949 (Unified Hangul Code)
+	/// extensions.
+	EucKr,
+
+	/// GBK (Simplified Chinese).
+	///
+	/// Decoded exactly like GB18030, of which it is a subset.
+	Gbk,
+
+	/// GB18030 (Simplified Chinese).
+	Gb18030,
+
+	/// Big5 (Traditional Chinese), including the HKSCS extensions.
+	Big5,
+}
+
+impl CjkEncoding {
+	/// Returns the name of the encoding.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::ShiftJis => "Shift_JIS",
+			Self::EucJp => "EUC-JP",
+			Self::EucKr => "EUC-KR",
+			Self::Gbk => "GBK",
+			Self::Gb18030 => "gb18030",
+			Self::Big5 => "Big5",
+		}
+	}
+}
+
+impl fmt::Display for CjkEncoding {
+	#[inline(always)]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.name().fmt(f)
+	}
+}
+
+/// Describes an invalid (or truncated) byte sequence found in a source file
+/// encoded with a multi-byte CJK encoding.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CjkError {
+	/// Encoding.
+	encoding: CjkEncoding,
+
+	/// Byte offset of the invalid sequence in the encoded source file.
+	offset: usize,
+
+	/// Byte length of the invalid sequence.
+	len: usize,
+
+	/// Whether or not the sequence is invalid because the input ended.
+	truncated: bool,
+}
+
+impl CjkError {
+	/// Returns the encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> CjkEncoding {
+		self.encoding
+	}
+
+	/// Returns the byte offset of the invalid sequence in the encoded source
+	/// file.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the byte length of the invalid sequence.
+	///
+	/// As defined by the WHATWG Encoding Standard, an ASCII byte ending an
+	/// invalid sequence is not part of it, and is decoded on its own. The
+	/// length is never zero.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Checks if the sequence is invalid only because the input ended before
+	/// it was complete.
+	#[inline(always)]
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+}
+
+impl fmt::Display for CjkError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.truncated {
+			write!(
+				f,
+				"incomplete {} sequence of {} byte(s) at offset {}",
+				self.encoding, self.len, self.offset
+			)
+		} else {
+			write!(
+				f,
+				"invalid {} sequence of {} byte(s) at offset {}",
+				self.encoding, self.len, self.offset
+			)
+		}
+	}
+}
+
+impl std::error::Error for CjkError {}
+
+/// Result of feeding a byte to a [`CjkMachine`].
+pub(crate) enum CjkStep {
+	/// The byte was consumed, but the sequence is not complete yet.
+	Pending,
+
+	/// A character has been decoded, with the given byte length.
+	Char(char, usize),
+
+	/// A 2 bytes long sequence has been decoded into two characters (Big5
+	/// only).
+	Chars(char, char),
+
+	/// Invalid sequence, with the given byte length.
+	Invalid(usize),
+}
+
+/// Converts an index code point into a character.
+///
+/// Unmapped pointers are given the code point `0xFFFF`, which never appears
+/// in the two-byte index tables.
+#[inline(always)]
+fn index_char(code_point: u32) -> Option<char> {
+	match code_point {
+		0xffff => None,
+		c => char::from_u32(c),
+	}
+}
+
+/// Returns the character of the pointers whose mapping was changed in the
+/// index gb18030 to follow GB18030-2022, which the index tables predate.
+#[inline(always)]
+fn gb18030_2022(pointer: u16) -> Option<char> {
+	match pointer {
+		7182 => Some('\u{fe10}'),
+		7183 => Some('\u{fe12}'),
+		7184 => Some('\u{fe11}'),
+		7185 => Some('\u{fe13}'),
+		7186 => Some('\u{fe14}'),
+		7187 => Some('\u{fe15}'),
+		7188 => Some('\u{fe16}'),
+		7201 => Some('\u{fe17}'),
+		7202 => Some('\u{fe18}'),
+		7208 => Some('\u{fe19}'),
+		7533 => Some('\u{1e3f}'),
+		23775 => Some('\u{9fb4}'),
+		23783 => Some('\u{9fb5}'),
+		23788 => Some('\u{9fb6}'),
+		23789 => Some('\u{9fb7}'),
+		23795 => Some('\u{9fb8}'),
+		23812 => Some('\u{9fb9}'),
+		23829 => Some('\u{9fba}'),
+		23845 => Some('\u{9fbb}'),
+		_ => None,
+	}
+}
+
+/// Multi-byte CJK decoding state machine.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CjkMachine {
+	encoding: CjkEncoding,
+
+	/// Bytes of the current sequence.
+	sequence: [u8; 3],
+
+	/// Number of bytes in `sequence`.
+	len: usize,
+
+	/// Bytes to process again after an invalid sequence, in reverse order.
+	deferred: [u8; 3],
+
+	/// Number of bytes in `deferred`.
+	deferred_len: usize,
+}
+
+impl CjkMachine {
+	#[inline(always)]
+	pub fn new(encoding: CjkEncoding) -> Self {
+		Self {
+			encoding,
+			sequence: [0; 3],
+			len: 0,
+			deferred: [0; 3],
+			deferred_len: 0,
+		}
+	}
+
+	/// Feeds the next byte to the machine.
+	///
+	/// [`Self::pop`] must be called until it returns `None` before feeding a
+	/// new byte.
+	pub fn push(&mut self, b: u8) -> CjkStep {
+		if self.len == 0 {
+			return self.push_first(b);
+		}
+
+		match self.encoding {
+			CjkEncoding::ShiftJis => {
+				let lead = self.sequence[0];
+				let c = match b {
+					0x40..=0x7e | 0x80..=0xfc => {
+						let offset = if b < 0x7f { 0x40 } else { 0x41 };
+						let lead_offset = if lead < 0xa0 { 0x81 } else { 0xc1 };
+						let pointer = (lead - lead_offset) as u16 * 188 + (b - offset) as u16;
+						if (8836..=10715).contains(&pointer) {
+							char::from_u32(0xe000 - 8836 + pointer as u32)
+						} else {
+							index_char(jis0208::forward(pointer))
+						}
+					}
+					_ => None,
+				};
+
+				self.end(b, c)
+			}
+			CjkEncoding::EucJp => {
+				let lead = self.sequence[self.len - 1];
+				match (self.len, lead, b) {
+					(1, 0x8e, 0xa1..=0xdf) => {
+						self.len = 0;
+						CjkStep::Char(char::from_u32(0xff61 - 0xa1 + b as u32).unwrap(), 2)
+					}
+					(1, 0x8f, 0xa1..=0xfe) => self.append(b),
+					_ => {
+						let c = match (lead, b) {
+							(0xa1..=0xfe, 0xa1..=0xfe) => {
+								let pointer = (lead - 0xa1) as u16 * 94 + (b - 0xa1) as u16;
+								if self.len == 2 {
+									index_char(jis0212::forward(pointer))
+								} else {
+									index_char(jis0208::forward(pointer))
+								}
+							}
+							_ => None,
+						};
+
+						self.end(b, c)
+					}
+				}
+			}
+			CjkEncoding::EucKr => {
+				let lead = self.sequence[0];
+				let c = match b {
+					0x41..=0xfe => {
+						let pointer = (lead - 0x81) as u16 * 190 + (b - 0x41) as u16;
+						index_char(euc_kr::forward(pointer))
+					}
+					_ => None,
+				};
+
+				self.end(b, c)
+			}
+			CjkEncoding::Gbk | CjkEncoding::Gb18030 => self.push_gb18030(b),
+			CjkEncoding::Big5 => {
+				let lead = self.sequence[0];
+				let c = match b {
+					0x40..=0x7e | 0xa1..=0xfe => {
+						let offset = if b < 0x7f { 0x40 } else { 0x62 };
+						let pointer = (lead - 0x81) as u16 * 157 + (b - offset) as u16;
+						let combined = match pointer {
+							1133 => Some(('\u{ca}', '\u{304}')),
+							1135 => Some(('\u{ca}', '\u{30c}')),
+							1164 => Some(('\u{ea}', '\u{304}')),
+							1166 => Some(('\u{ea}', '\u{30c}')),
+							_ => None,
+						};
+
+						if let Some((a, b)) = combined {
+							self.len = 0;
+							return CjkStep::Chars(a, b);
+						}
+
+						index_char(big5::forward(pointer))
+					}
+					_ => None,
+				};
+
+				self.end(b, c)
+			}
+		}
+	}
+
+	/// Feeds the first byte of a sequence.
+	fn push_first(&mut self, b: u8) -> CjkStep {
+		let c = match (self.encoding, b) {
+			(_, 0x00..=0x7f) => b as char,
+			(CjkEncoding::ShiftJis, 0x80) => b as char,
+			(CjkEncoding::ShiftJis, 0xa1..=0xdf) => {
+				char::from_u32(0xff61 - 0xa1 + b as u32).unwrap()
+			}
+			(CjkEncoding::ShiftJis, 0x81..=0x9f | 0xe0..=0xfc) => return self.append(b),
+			(CjkEncoding::EucJp, 0x8e | 0x8f | 0xa1..=0xfe) => return self.append(b),
+			(CjkEncoding::Gbk | CjkEncoding::Gb18030, 0x80) => '\u{20ac}',
+			(
+				CjkEncoding::EucKr | CjkEncoding::Gbk | CjkEncoding::Gb18030 | CjkEncoding::Big5,
+				0x81..=0xfe,
+			) => return self.append(b),
+			_ => return CjkStep::Invalid(1),
+		};
+
+		CjkStep::Char(c, 1)
+	}
+
+	/// Feeds a byte following the first byte of a GB18030 sequence.
+	fn push_gb18030(&mut self, b: u8) -> CjkStep {
+		match (self.len, b) {
+			(1, 0x30..=0x39) | (2, 0x81..=0xfe) => self.append(b),
+			(1, _) => {
+				let lead = self.sequence[0];
+				let c = match b {
+					0x40..=0x7e | 0x80..=0xfe => {
+						let offset = if b < 0x7f { 0x40 } else { 0x41 };
+						let pointer = (lead - 0x81) as u16 * 190 + (b - offset) as u16;
+						gb18030_2022(pointer).or_else(|| index_char(gb18030::forward(pointer)))
+					}
+					_ => None,
+				};
+
+				self.end(b, c)
+			}
+			(3, 0x30..=0x39) => {
+				let [first, second, third] = self.sequence;
+				let pointer = (first - 0x81) as u32 * 12600
+					+ (second - 0x30) as u32 * 1260
+					+ (third - 0x81) as u32 * 10
+					+ (b - 0x30) as u32;
+				let c = match pointer {
+					7457 => Some('\u{e7c7}'),
+					pointer => match gb18030_ranges::forward(pointer) {
+						0xffffffff => None,
+						c => char::from_u32(c),
+					},
+				};
+
+				self.len = 0;
+				match c {
+					Some(c) => CjkStep::Char(c, 4),
+					None => CjkStep::Invalid(4),
+				}
+			}
+			_ => {
+				// Only the first byte is invalid, the others are processed
+				// again.
+				let len = self.len;
+				self.len = 0;
+				self.defer(b);
+				for i in (1..len).rev() {
+					self.defer(self.sequence[i])
+				}
+
+				CjkStep::Invalid(1)
+			}
+		}
+	}
+
+	/// Appends the given byte to the current sequence.
+	#[inline(always)]
+	fn append(&mut self, b: u8) -> CjkStep {
+		self.sequence[self.len] = b;
+		self.len += 1;
+		CjkStep::Pending
+	}
+
+	/// Ends the current sequence with the given byte, decoded as `c`.
+	///
+	/// If the sequence is invalid and `b` is an ASCII byte, it is not part of
+	/// the sequence and is processed again.
+	#[inline(always)]
+	fn end(&mut self, b: u8, c: Option<char>) -> CjkStep {
+		let len = std::mem::take(&mut self.len) + 1;
+		match c {
+			Some(c) => CjkStep::Char(c, len),
+			None if b.is_ascii() => {
+				self.defer(b);
+				CjkStep::Invalid(len - 1)
+			}
+			None => CjkStep::Invalid(len),
+		}
+	}
+
+	/// Schedules the given byte to be processed again, before the bytes
+	/// already deferred.
+	#[inline(always)]
+	fn defer(&mut self, b: u8) {
+		self.deferred[self.deferred_len] = b;
+		self.deferred_len += 1;
+	}
+
+	/// Processes the next deferred byte, if any.
+	#[inline(always)]
+	pub fn pop(&mut self) -> Option<CjkStep> {
+		if self.deferred_len > 0 {
+			self.deferred_len -= 1;
+			Some(self.push(self.deferred[self.deferred_len]))
+		} else {
+			None
+		}
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Returns the byte length of the truncated sequence, if any.
+	#[inline(always)]
+	pub fn finish(&mut self) -> Option<usize> {
+		let len = std::mem::take(&mut self.len);
+		if len > 0 {
+			Some(len)
+		} else {
+			None
+		}
+	}
+}
+
+/// Decoder for bytes encoded with a multi-byte CJK encoding.
+///
+/// The length of each decoded character is the byte length of its sequence
+/// in the encoded source file, from 1 to 4 bytes. The four Big5 sequences
+/// decoded into two characters (a letter followed by a combining mark) yield
+/// the letter with a length of 2 bytes, and the combining mark with a length
+/// of 0.
+#[derive(Clone, Debug)]
+pub struct CjkDecoded<B> {
+	/// Encoded bytes.
+	bytes: B,
+
+	/// Decoding state.
+	machine: CjkMachine,
+
+	/// Combining mark following the last decoded character (Big5 only).
+	mark: Option<char>,
+
+	/// Byte offset of the next sequence.
+	offset: usize,
+}
+
+impl<B> CjkDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: CjkEncoding) -> Self {
+		Self::with_start(bytes, encoding, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	#[inline(always)]
+	pub fn with_start(bytes: B, encoding: CjkEncoding, offset: usize) -> Self {
+		Self {
+			bytes,
+			machine: CjkMachine::new(encoding),
+			mark: None,
+			offset,
+		}
+	}
+
+	/// Returns the encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> CjkEncoding {
+		self.machine.encoding
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Turns this decoder into a lossy decoder, replacing invalid sequences
+	/// with `U+FFFD REPLACEMENT CHARACTER`.
+	#[inline(always)]
+	pub fn lossy(self) -> LossyCjkDecoded<B> {
+		LossyCjkDecoded(self)
+	}
+
+	fn emit(&mut self, step: CjkStep) -> Option<Result<DecodedChar, CjkError>> {
+		let offset = self.offset;
+		match step {
+			CjkStep::Pending => None,
+			CjkStep::Char(c, len) => {
+				self.offset += len;
+				Some(Ok(DecodedChar::new(c, ByteLen(len))))
+			}
+			CjkStep::Chars(c, mark) => {
+				self.offset += 2;
+				self.mark = Some(mark);
+				Some(Ok(DecodedChar::new(c, ByteLen(2))))
+			}
+			CjkStep::Invalid(len) => {
+				self.offset += len;
+				Some(Err(CjkError {
+					encoding: self.machine.encoding,
+					offset,
+					len,
+					truncated: false,
+				}))
+			}
+		}
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for CjkDecoded<B> {
+	type Item = Result<DecodedChar, CjkError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if let Some(mark) = self.mark.take() {
+			return Some(Ok(DecodedChar::new(mark, ByteLen(0))));
+		}
+
+		loop {
+			let step = match self.machine.pop() {
+				Some(step) => step,
+				None => match self.bytes.next() {
+					Some(b) => self.machine.push(b),
+					None => {
+						let offset = self.offset;
+						break self.machine.finish().map(|len| {
+							self.offset += len;
+							Err(CjkError {
+								encoding: self.machine.encoding,
+								offset,
+								len,
+								truncated: true,
+							})
+						});
+					}
+				},
+			};
+
+			if let Some(item) = self.emit(step) {
+				break Some(item);
+			}
+		}
+	}
+}
+
+/// Lossy decoder for bytes encoded with a multi-byte CJK encoding.
+///
+/// Behaves like [`CjkDecoded`], but replaces each invalid sequence with a
+/// `U+FFFD REPLACEMENT CHARACTER` of the same byte length.
+#[derive(Clone, Debug)]
+pub struct LossyCjkDecoded<B>(CjkDecoded<B>);
+
+impl<B> LossyCjkDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: CjkEncoding) -> Self {
+		Self(CjkDecoded::new(bytes, encoding))
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.0.offset()
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for LossyCjkDecoded<B> {
+	type Item = DecodedChar;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|result| {
+			result
+				.unwrap_or_else(|e| DecodedChar::new(char::REPLACEMENT_CHARACTER, ByteLen(e.len())))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	type Item = Result<(char, usize), (usize, usize, bool)>;
+
+	/// Decodes the given bytes, returning each character with its length, or
+	/// the offset, length and truncation of each error.
+	fn decode(bytes: &[u8], encoding: CjkEncoding) -> Vec<Item> {
+		CjkDecoded::new(bytes.iter().copied(), encoding)
+			.map(|item| match item {
+				Ok(c) => Ok((c.chr(), c.len().0)),
+				Err(e) => Err((e.offset(), e.len(), e.is_truncated())),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn known_answers() {
+		use CjkEncoding::*;
+		let cases: [(_, &[u8], &[_]); 6] = [
+			(ShiftJis, b"\x82\xa0\x93\xfa", &[('あ', 2), ('日', 2)]),
+			(
+				EucJp,
+				b"\xa4\xa2\x8f\xb0\xa1\x8e\xb1",
+				&[('あ', 2), ('丂', 3), ('ｱ', 2)],
+			),
+			(EucKr, b"\xb0\xa1\x81\x41", &[('가', 2), ('갂', 2)]),
+			(
+				Gbk,
+				b"\xc4\xe3\x81\x30\x81\x30",
+				&[('你', 2), ('\u{80}', 4)],
+			),
+			(
+				Gb18030,
+				b"\xc4\xe3\x81\x30\x81\x30\x80",
+				&[('你', 2), ('\u{80}', 4), ('€', 1)],
+			),
+			(
+				Big5,
+				b"\xa4\x40\x88\x62",
+				&[('一', 2), ('Ê', 2), ('\u{304}', 0)],
+			),
+		];
+
+		for (encoding, bytes, expected) in cases {
+			let expected: Vec<_> = expected.iter().copied().map(Ok).collect();
+			assert_eq!(decode(bytes, encoding), expected, "{encoding}");
+		}
+	}
+
+	#[test]
+	fn errors() {
+		// The ASCII trail byte of an invalid sequence is decoded on its own.
+		assert_eq!(
+			decode(b"\x85\x41a\x82", CjkEncoding::ShiftJis),
+			[
+				Err((0, 1, false)),
+				Ok(('A', 1)),
+				Ok(('a', 1)),
+				Err((3, 1, true))
+			]
+		);
+
+		let e = CjkDecoded::with_start(b"\x82".iter().copied(), CjkEncoding::ShiftJis, 4)
+			.next()
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(
+			e.to_string(),
+			"incomplete Shift_JIS sequence of 1 byte(s) at offset 4"
+		);
+	}
+
+	#[test]
+	fn lengths_add_up() {
+		use CjkEncoding::*;
+		let bytes = b"a\x82\xa0\x8f\xb0\xa1\x81\x30\x81\x30\x88\x62\xff\x85";
+		for encoding in [ShiftJis, EucJp, EucKr, Gbk, Gb18030, Big5] {
+			let len: usize = LossyCjkDecoded::new(bytes.iter().copied(), encoding)
+				.map(|c| c.len().0)
+				.sum();
+			assert_eq!(len, bytes.len(), "{encoding}");
+		}
+	}
+}
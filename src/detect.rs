@@ -0,0 +1,644 @@
+#[cfg(feature = "cjk")]
+use crate::CjkEncoding;
+use crate::{
+	DecodeError, Encoding, EncodingDecoded, SingleByteEncoding, UnicodeEncoding, Utf16Error,
+	Utf32Error,
+};
+
+/// Single-byte encodings considered by the detector, by order of preference.
+///
+/// ISO-8859-1, ISO-8859-9 and ISO-8859-15 are left out as their printable
+/// characters are (mostly) covered by Windows-1252 and Windows-1254.
+const SINGLE_BYTE_CANDIDATES: [SingleByteEncoding; 15] = [
+	SingleByteEncoding::Windows1252,
+	SingleByteEncoding::Windows1250,
+	SingleByteEncoding::Windows1251,
+	SingleByteEncoding::Windows1253,
+	SingleByteEncoding::Windows1254,
+	SingleByteEncoding::Windows1255,
+	SingleByteEncoding::Windows1256,
+	SingleByteEncoding::Windows1257,
+	SingleByteEncoding::Windows874,
+	SingleByteEncoding::Koi8U,
+	SingleByteEncoding::Ibm866,
+	SingleByteEncoding::Iso8859_2,
+	SingleByteEncoding::Iso8859_5,
+	SingleByteEncoding::Iso8859_7,
+	SingleByteEncoding::Windows1258,
+];
+
+/// CJK encodings considered by the detector, by order of preference.
+///
+/// GB18030 is left out as it decodes exactly like GBK.
+#[cfg(feature = "cjk")]
+const CJK_CANDIDATES: [CjkEncoding; 5] = [
+	CjkEncoding::ShiftJis,
+	CjkEncoding::EucJp,
+	CjkEncoding::EucKr,
+	CjkEncoding::Gbk,
+	CjkEncoding::Big5,
+];
+
+/// Frequent lowercase letters of the languages written with each script,
+/// rewarded by the scoring.
+const FREQUENT_LETTERS: &str = "éèàçáíóúñãâêôäöüßąęłśżćńčšžřěůőűğışėųū\
+	оеаинтсрвлкмдпуяіїєґ\
+	αοετινσρκπςυμλ\
+	יוהלארמתבנש\
+	اليمنوهرتبعد";
+
+/// Non-ASCII punctuation and symbols common enough in texts to not be
+/// penalized by the scoring.
+const PUNCTUATION: &str = "\u{a0}«»–—‘’‚“”„…•°©®€·§¿¡№™×\
+	\u{5be}\u{5c0}\u{5c3}\u{60c}\u{61b}\u{61f}\u{640}\u{e3f}\u{e4f}\u{e5a}\u{e5b}";
+
+/// Letters only written at the end of words.
+const FINAL_FORMS: &str = "ςךםןףץ";
+
+/// Returns the lowercase non-ASCII Latin letters used by the languages
+/// written with the given encoding.
+///
+/// Other Latin letters are scored as symbols, which tells apart the Latin
+/// encodings decoding the same bytes as different letters.
+fn latin_letters(encoding: SingleByteEncoding) -> &'static str {
+	match encoding {
+		SingleByteEncoding::Windows1252 => "àáâãäåæçèéêëìíîïñòóôõöøùúûüÿœß",
+		SingleByteEncoding::Windows1250 | SingleByteEncoding::Iso8859_2 => {
+			"áäâăąćčďéęëěíîĺľłńňóôöőŕřśšşťţúůüűýźżž"
+		}
+		SingleByteEncoding::Windows1254 => "àáâäçèéêëîïñòóôöùúûüğışœß",
+		SingleByteEncoding::Windows1257 => "äåæāąčćēėęģīįķļłņńõöōšśūųüźżžéó",
+		SingleByteEncoding::Windows1258 => {
+			"àáâãèéêìíòóôõùúýăđơư\u{300}\u{301}\u{303}\u{309}\u{323}"
+		}
+		_ => "",
+	}
+}
+
+/// Characters of the Japanese, Chinese and Korean languages that are frequent
+/// enough to be found in most texts, rewarded by the scoring of the
+/// corresponding encodings.
+#[cfg(feature = "cjk")]
+const FREQUENT_JAPANESE: &str =
+	"のにはをたがでてとしれさるいなかもすらっあこうりまくきけ、。一人日";
+#[cfg(feature = "cjk")]
+const FREQUENT_CHINESE: &str = "的一是不了在人有我他这个们中来上大为和国地到以说时要就出会可也你对生能而子那得于着下自之年过发后作里";
+#[cfg(feature = "cjk")]
+const FREQUENT_TRADITIONAL_CHINESE: &str = "的一是不了在人有我他這個們中來上大為和國地到以說時要就出會可也你對生能而子那得於著下自之年過發後作裡";
+#[cfg(feature = "cjk")]
+const FREQUENT_KOREAN: &str =
+	"이다는의에가을하고를지한로기서있사도으수들리대인아게해것나정시적어일";
+
+/// Result of the heuristic detection of an encoding.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Detection {
+	/// Most likely encoding.
+	encoding: Encoding,
+
+	/// Confidence, between 0 and 1.
+	confidence: f32,
+}
+
+impl Detection {
+	/// Returns the most likely encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> Encoding {
+		self.encoding
+	}
+
+	/// Returns the confidence in the detected encoding, between 0 (the
+	/// encoding is a mere guess) and 1 (no other candidate encoding decodes the
+	/// input differently and plausibly).
+	#[inline(always)]
+	pub fn confidence(&self) -> f32 {
+		self.confidence
+	}
+
+	/// Creates a decoder for the given bytes using the detected encoding.
+	///
+	/// The bytes are decoded as they are: a byte order mark is decoded as a
+	/// `U+FEFF` character.
+	#[inline(always)]
+	pub fn decode<B: IntoIterator<Item = u8>>(&self, bytes: B) -> EncodingDecoded<B::IntoIter> {
+		EncodingDecoded::new(bytes.into_iter(), self.encoding)
+	}
+}
+
+/// Guesses the encoding of the given bytes, which usually are the first few
+/// kilobytes of the input.
+///
+/// The encoding is given by the byte order mark if any. Otherwise, valid
+/// UTF-8 (or ASCII) input is detected as UTF-8, and UTF-16 is recognized from
+/// the distribution of its null bytes. Legacy encodings are then scored on
+/// the plausibility of the decoded text: invalid sequences disqualify an
+/// encoding, while words made of letters of the same script, frequent
+/// characters and sequences are rewarded, and symbols, control characters,
+/// misplaced uppercase letters or final forms and mixed scripts are
+/// penalized. Ties are broken by the number of frequent characters. The CJK
+/// encodings are only considered with the `cjk` feature.
+///
+/// Truncated sequences at the end of `bytes` are ignored, so it can be a
+/// prefix of the input cut at any position.
+pub fn detect(bytes: &[u8]) -> Detection {
+	if let Some(encoding) = UnicodeEncoding::sniff_bom(bytes) {
+		return Detection {
+			encoding: encoding.into(),
+			confidence: 1.0,
+		};
+	}
+
+	if let Some(detection) = detect_utf16(bytes) {
+		return detection;
+	}
+
+	if let Some(sequences) = utf8_sequences(bytes) {
+		return Detection {
+			encoding: UnicodeEncoding::Utf8.into(),
+			confidence: if sequences == 0 {
+				1.0
+			} else {
+				1.0 - 0.5f32.powi(sequences.min(64) as i32 + 1)
+			},
+		};
+	}
+
+	let candidates = SINGLE_BYTE_CANDIDATES.into_iter().map(Encoding::SingleByte);
+	#[cfg(feature = "cjk")]
+	let candidates = candidates.chain(CJK_CANDIDATES.into_iter().map(Encoding::Cjk));
+
+	let mut scored: Vec<(Encoding, Score, String)> = candidates
+		.filter_map(|encoding| score(encoding, bytes).map(|(score, text)| (encoding, score, text)))
+		.collect();
+
+	// Stable sort: ties on both scores are won by the preferred encoding.
+	scored.sort_by_key(|(_, score, _)| std::cmp::Reverse((score.plausibility, score.frequent)));
+
+	match scored.first() {
+		Some((encoding, best, text)) => {
+			let best = best.plausibility;
+			let second = scored
+				.iter()
+				.find(|(_, _, other)| other != text)
+				.map(|(_, score, _)| score.plausibility.max(0))
+				.unwrap_or(0);
+			let margin = (best - second).max(0) as f32;
+
+			Detection {
+				encoding: *encoding,
+				confidence: margin / (margin + 10.0),
+			}
+		}
+		None => Detection {
+			encoding: SingleByteEncoding::Windows1252.into(),
+			confidence: 0.0,
+		},
+	}
+}
+
+/// Detects UTF-16 without byte order mark from the distribution of null
+/// bytes, frequent in texts using mostly ASCII or Latin-1 characters.
+fn detect_utf16(bytes: &[u8]) -> Option<Detection> {
+	let units = bytes.len() / 2;
+	if units < 2 {
+		return None;
+	}
+
+	let (mut even, mut odd) = (0, 0);
+	for pair in bytes.chunks_exact(2) {
+		even += (pair[0] == 0) as usize;
+		odd += (pair[1] == 0) as usize;
+	}
+
+	let (encoding, zeros, others) = if odd > even {
+		(UnicodeEncoding::Utf16Le, odd, even)
+	} else {
+		(UnicodeEncoding::Utf16Be, even, odd)
+	};
+
+	if zeros * 4 < units || others * 20 > units {
+		return None;
+	}
+
+	let valid =
+		EncodingDecoded::new(bytes.iter().copied(), encoding.into()).all(|item| match item {
+			Ok(c) => c.chr() != '\0',
+			Err(e) => is_truncated(&e),
+		});
+
+	valid.then(|| Detection {
+		encoding: encoding.into(),
+		confidence: zeros as f32 / units as f32,
+	})
+}
+
+/// Returns the number of non-ASCII sequences in the given bytes, or `None`
+/// if they are not valid UTF-8.
+fn utf8_sequences(bytes: &[u8]) -> Option<usize> {
+	let mut count = 0;
+	for item in EncodingDecoded::new(bytes.iter().copied(), UnicodeEncoding::Utf8.into()) {
+		match item {
+			Ok(c) => count += !c.is_ascii() as usize,
+			Err(e) if is_truncated(&e) => (),
+			Err(_) => return None,
+		}
+	}
+
+	Some(count)
+}
+
+/// Checks if the given error is only caused by the end of the input.
+fn is_truncated(e: &DecodeError) -> bool {
+	match e {
+		DecodeError::Utf8(e) => e.is_truncated(),
+		DecodeError::Utf16(Utf16Error::Truncated { .. }) => true,
+		DecodeError::Utf32(Utf32Error::Truncated { .. }) => true,
+		#[cfg(feature = "cjk")]
+		DecodeError::Cjk(e) => e.is_truncated(),
+		_ => false,
+	}
+}
+
+/// Writing system of a letter.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Script {
+	Latin,
+	Greek,
+	Cyrillic,
+	Hebrew,
+	Arabic,
+	Thai,
+	Other,
+}
+
+impl Script {
+	fn of(c: char) -> Self {
+		match c {
+			'\0'..='\u{24f}' | '\u{300}'..='\u{36f}' | '\u{1e00}'..='\u{1eff}' => Self::Latin,
+			'\u{370}'..='\u{3ff}' | '\u{1f00}'..='\u{1fff}' => Self::Greek,
+			'\u{400}'..='\u{52f}' => Self::Cyrillic,
+			'\u{590}'..='\u{5ff}' | '\u{fb1d}'..='\u{fb4f}' => Self::Hebrew,
+			'\u{600}'..='\u{6ff}' | '\u{fb50}'..='\u{fdff}' | '\u{fe70}'..='\u{feff}' => {
+				Self::Arabic
+			}
+			'\u{e00}'..='\u{e7f}' => Self::Thai,
+			_ => Self::Other,
+		}
+	}
+}
+
+/// Letter, classified for scoring.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Letter {
+	script: Script,
+	ascii: bool,
+	lowercase: bool,
+	uppercase: bool,
+	final_form: bool,
+}
+
+impl Letter {
+	fn new(c: char) -> Option<Self> {
+		let is_letter = c.is_alphabetic()
+			|| matches!(
+				c,
+				'\u{300}'..='\u{36f}'
+					| '\u{591}'..='\u{5bd}'
+					| '\u{5bf}'..='\u{5c7}'
+					| '\u{610}'..='\u{61a}'
+					| '\u{64b}'..='\u{65f}'
+					| '\u{e31}'..='\u{e3a}'
+					| '\u{e47}'..='\u{e4e}'
+			);
+
+		is_letter.then(|| Self {
+			script: Script::of(c),
+			ascii: c.is_ascii(),
+			lowercase: c.is_lowercase(),
+			uppercase: c.is_uppercase(),
+			final_form: FINAL_FORMS.contains(c),
+		})
+	}
+}
+
+/// Scores of a candidate encoding.
+#[derive(Clone, Copy, Default, Debug)]
+struct Score {
+	/// Plausibility of the decoded text.
+	plausibility: i64,
+
+	/// Number of frequent characters in the decoded text, breaking ties
+	/// between equally plausible encodings.
+	frequent: usize,
+}
+
+/// Decodes the given bytes with the given encoding and scores the result.
+///
+/// Returns `None` if the bytes are not valid in this encoding.
+fn score(encoding: Encoding, bytes: &[u8]) -> Option<(Score, String)> {
+	let mut text = String::new();
+	let mut score = Score::default();
+	let mut previous = None;
+
+	let mut decoded = EncodingDecoded::new(bytes.iter().copied(), encoding);
+	loop {
+		let offset = decoded.offset();
+		match decoded.next() {
+			Some(Ok(c)) => {
+				#[cfg_attr(not(feature = "cjk"), allow(unused_variables))]
+				let sequence = &bytes[offset..(offset + c.len().0)];
+				score.plausibility += match encoding {
+					#[cfg(feature = "cjk")]
+					Encoding::Cjk(e) => cjk_score(e, sequence, c.chr()),
+					Encoding::SingleByte(e) => text_score(e, previous, c.chr()),
+					Encoding::Unicode(_) => 0,
+				};
+
+				if frequent_chars(encoding).contains(c.chr()) {
+					score.frequent += 1
+				}
+
+				previous = Letter::new(c.chr());
+				text.push(c.chr())
+			}
+			Some(Err(e)) if is_truncated(&e) => break,
+			Some(Err(_)) => return None,
+			None => break,
+		}
+	}
+
+	// A final form ending the text ends a word.
+	if previous.is_some_and(|p: Letter| p.final_form) {
+		score.plausibility += 2
+	}
+
+	Some((score, text))
+}
+
+/// Returns the frequent characters of the languages written with the given
+/// encoding.
+fn frequent_chars(encoding: Encoding) -> &'static str {
+	match encoding {
+		#[cfg(feature = "cjk")]
+		Encoding::Cjk(CjkEncoding::ShiftJis | CjkEncoding::EucJp) => FREQUENT_JAPANESE,
+		#[cfg(feature = "cjk")]
+		Encoding::Cjk(CjkEncoding::EucKr) => FREQUENT_KOREAN,
+		#[cfg(feature = "cjk")]
+		Encoding::Cjk(CjkEncoding::Gbk | CjkEncoding::Gb18030) => FREQUENT_CHINESE,
+		#[cfg(feature = "cjk")]
+		Encoding::Cjk(CjkEncoding::Big5) => FREQUENT_TRADITIONAL_CHINESE,
+		Encoding::SingleByte(_) => FREQUENT_LETTERS,
+		Encoding::Unicode(_) => "",
+	}
+}
+
+/// Scores the plausibility of the character `c` decoded from the given
+/// single-byte encoding, following a letter (or `None`).
+fn text_score(encoding: SingleByteEncoding, previous: Option<Letter>, c: char) -> i64 {
+	let letter = Letter::new(c).filter(|letter| {
+		letter.ascii
+			|| letter.script != Script::Latin
+			|| c.to_lowercase()
+				.all(|c| latin_letters(encoding).contains(c))
+	});
+
+	match letter {
+		Some(letter) => {
+			let mut score = match previous {
+				Some(previous) if previous.script != letter.script => -5,
+				Some(_) if letter.ascii => 0,
+				Some(previous) if letter.script == Script::Latin => 1 + 2 * previous.ascii as i64,
+				Some(_) => 2,
+				None if letter.ascii => 0,
+				None => 1,
+			};
+
+			if previous.is_some_and(|p| p.lowercase) && letter.uppercase {
+				score -= 3
+			}
+
+			if previous.is_some_and(|p| p.final_form) {
+				score -= 3
+			}
+
+			if FREQUENT_LETTERS.contains(c) {
+				score += 1
+			}
+
+			score
+		}
+		None if previous.is_some_and(|p| p.final_form) => 2,
+		None => match c {
+			'\0'..='\u{7f}' => 0,
+			'\u{80}'..='\u{9f}' | '\u{e000}'..='\u{f8ff}' => -10,
+			c if PUNCTUATION.contains(c) => 0,
+			'\u{660}'..='\u{669}' | '\u{e50}'..='\u{e59}' => 0,
+			_ => -2,
+		},
+	}
+}
+
+/// Scores the plausibility of the character `c` decoded from the given
+/// sequence of a CJK encoding.
+///
+/// The most frequent characters of each language are rewarded, then
+/// characters are scored according to the region of the encoding they come
+/// from: kana, symbols, common ideographs (or Hangul syllables), less
+/// common ideographs, and rarely used extensions.
+#[cfg(feature = "cjk")]
+fn cjk_score(encoding: CjkEncoding, sequence: &[u8], c: char) -> i64 {
+	// The second character of a Big5 sequence decoding as two characters is
+	// empty: it is scored with the first one.
+	if c.is_ascii() || sequence.is_empty() {
+		return 0;
+	}
+
+	if matches!(c, '\u{80}'..='\u{9f}' | '\u{e000}'..='\u{f8ff}') {
+		return -10;
+	}
+
+	let bonus = if frequent_chars(encoding.into()).contains(c) {
+		4
+	} else {
+		0
+	};
+
+	let lead = sequence[0];
+	let trail = sequence.get(1).copied().unwrap_or(0);
+	let region = match (encoding, sequence.len()) {
+		(_, 1) => -1,
+		(CjkEncoding::ShiftJis, 2) => match lead {
+			0x82 | 0x83 => 4,
+			0x81 => 2,
+			0x88..=0x98 => 3,
+			0x99..=0x9f | 0xe0..=0xea => 1,
+			0x84 | 0x87 => 0,
+			_ => -4,
+		},
+		(CjkEncoding::EucJp, 2) => match lead {
+			0xa4 | 0xa5 => 4,
+			0xa1 => 2,
+			0xb0..=0xcf => 3,
+			0xd0..=0xf4 => 1,
+			0x8e => -1,
+			_ => 0,
+		},
+		(CjkEncoding::EucKr, 2) => match (lead, trail) {
+			(0xb0..=0xc8, 0xa1..) => 4,
+			(0xa1, 0xa1..) => 2,
+			(0xca..=0xfd, 0xa1..) => 1,
+			(0xa2..=0xaf, 0xa1..) => 0,
+			_ => -2,
+		},
+		(CjkEncoding::Gbk | CjkEncoding::Gb18030, 2) => match (lead, trail) {
+			(0xb0..=0xd7, 0xa1..) => 3,
+			(0xa1..=0xa3, 0xa1..) => 2,
+			(0xd8..=0xf7, 0xa1..) => 1,
+			_ => -2,
+		},
+		(CjkEncoding::Big5, 2) => match lead {
+			0xa4..=0xc6 => 3,
+			0xa1..=0xa3 => 2,
+			0xc9..=0xf9 => 1,
+			_ => -2,
+		},
+		_ => -4,
+	};
+
+	region + bonus
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Encodes the given text with the given single-byte encoding.
+	fn encode(text: &str, encoding: SingleByteEncoding) -> Vec<u8> {
+		text.chars()
+			.map(|c| {
+				(0..=255u8)
+					.find(|&b| encoding.decode_byte(b) == Some(c))
+					.unwrap()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn unicode() {
+		let detection = detect(b"\xff\xfea\x00");
+		assert_eq!(detection.encoding(), UnicodeEncoding::Utf16Le.into());
+		assert_eq!(detection.confidence(), 1.0);
+
+		assert_eq!(
+			detect(b"plain ASCII").encoding(),
+			UnicodeEncoding::Utf8.into()
+		);
+		assert_eq!(
+			detect("déjà vu".as_bytes()).encoding(),
+			UnicodeEncoding::Utf8.into()
+		);
+		// Truncated sequence at the end.
+		assert_eq!(
+			detect(b"d\xc3\xa9j\xc3").encoding(),
+			UnicodeEncoding::Utf8.into()
+		);
+
+		let utf16: Vec<u8> = "hello, world"
+			.encode_utf16()
+			.flat_map(u16::to_be_bytes)
+			.collect();
+		assert_eq!(detect(&utf16).encoding(), UnicodeEncoding::Utf16Be.into());
+	}
+
+	#[test]
+	fn single_byte() {
+		use SingleByteEncoding::*;
+		let cases = [
+			("Le café était très bon, à bientôt !", Windows1252),
+			("Příliš žluťoučký kůň úpěl ďábelské ódy.", Windows1250),
+			("Съешь же ещё этих мягких французских булок.", Windows1251),
+			("Ξεσκεπάζω την ψυχοφθόρα βδελυγμία.", Windows1253),
+		];
+
+		for (text, encoding) in cases {
+			let detection = detect(&encode(text, encoding));
+			assert_eq!(detection.encoding(), encoding.into(), "{text}");
+			assert!(detection.confidence() > 0.0, "{text}");
+		}
+	}
+
+	#[test]
+	fn latin1_control_characters() {
+		// Bytes Windows-1252 leaves undefined decode as C1 control characters,
+		// which do not disqualify it.
+		let mut bytes = encode("Le café était très bon,", SingleByteEncoding::Iso8859_1);
+		bytes.push(0x81);
+		bytes.extend(encode(" à bientôt !", SingleByteEncoding::Iso8859_1));
+		bytes.push(0x90);
+
+		let detection = detect(&bytes);
+		assert_eq!(detection.encoding(), SingleByteEncoding::Windows1252.into());
+		let decoded: String = detection
+			.decode(bytes.iter().copied())
+			.map(|c| c.unwrap().chr())
+			.collect();
+		assert_eq!(decoded, "Le café était très bon,\u{81} à bientôt !\u{90}");
+	}
+
+	#[test]
+	fn hebrew() {
+		for text in ["שלום עולם", "עברית היא שפה שמית."] {
+			let detection = detect(&encode(text, SingleByteEncoding::Windows1255));
+			assert_eq!(
+				detection.encoding(),
+				SingleByteEncoding::Windows1255.into(),
+				"{text}"
+			);
+			assert!(detection.confidence() > 0.0, "{text}");
+		}
+	}
+
+	#[cfg(feature = "cjk")]
+	#[test]
+	fn cjk() {
+		use CjkEncoding::*;
+		let japanese = "日本語の文章を正しく判定できるかどうかを確認します。";
+		let cases: [(&[u8], _, _); 5] = [
+			(
+				b"\x93\xfa\x96{\x8c\xea\x82\xcc\x95\xb6\x8f\xcd\x82\xf0\x90\xb3\x82\xb5\x82\xad\x94\xbb\x92\xe8\x82\xc5\x82\xab\x82\xe9\x82\xa9\x82\xc7\x82\xa4\x82\xa9\x82\xf0\x8am\x94F\x82\xb5\x82\xdc\x82\xb7\x81B",
+				ShiftJis,
+				japanese,
+			),
+			(
+				b"\xc6\xfc\xcb\xdc\xb8\xec\xa4\xce\xca\xb8\xbe\xcf\xa4\xf2\xc0\xb5\xa4\xb7\xa4\xaf\xc8\xbd\xc4\xea\xa4\xc7\xa4\xad\xa4\xeb\xa4\xab\xa4\xc9\xa4\xa6\xa4\xab\xa4\xf2\xb3\xce\xc7\xa7\xa4\xb7\xa4\xde\xa4\xb9\xa1\xa3",
+				EucJp,
+				japanese,
+			),
+			(
+				b"\xc7\xd1\xb1\xb9\xbe\xee \xb9\xae\xc0\xe5\xc0\xbb \xbf\xc3\xb9\xd9\xb8\xa3\xb0\xd4 \xc6\xc7\xba\xb0\xc7\xd2 \xbc\xf6 \xc0\xd6\xb4\xc2\xc1\xf6 \xc8\xae\xc0\xce\xc7\xd5\xb4\xcf\xb4\xd9.",
+				EucKr,
+				"한국어 문장을 올바르게 판별할 수 있는지 확인합니다.",
+			),
+			(
+				b"\xce\xd2\xc3\xc7\xd5\xfd\xd4\xda\xbc\xec\xb2\xe9\xd5\xe2\xb8\xf6\xd6\xd0\xce\xc4\xbe\xe4\xd7\xd3\xca\xc7\xb7\xf1\xc4\xdc\xb1\xbb\xd5\xfd\xc8\xb7\xb5\xd8\xca\xb6\xb1\xf0\xb3\xf6\xc0\xb4\xa1\xa3",
+				Gbk,
+				"我们正在检查这个中文句子是否能被正确地识别出来。",
+			),
+			(
+				b"\xa7\xda\xad\xcc\xa5\xbf\xa6b\xc0\xcb\xacd\xb3o\xad\xd3\xa4\xa4\xa4\xe5\xa5y\xa4l\xacO\xa7_\xaf\xe0\xb3Q\xa5\xbf\xbdT\xa6a\xc3\xd1\xa7O\xa5X\xa8\xd3\xa1C",
+				Big5,
+				"我們正在檢查這個中文句子是否能被正確地識別出來。",
+			),
+		];
+
+		for (bytes, encoding, text) in cases {
+			let detection = detect(bytes);
+			assert_eq!(detection.encoding(), encoding.into(), "{text}");
+			assert!(detection.confidence() > 0.0, "{text}");
+			let decoded: String = detection
+				.decode(bytes.iter().copied())
+				.map(|c| c.unwrap().chr())
+				.collect();
+			assert_eq!(decoded, text);
+		}
+	}
+}
@@ -0,0 +1,336 @@
+#[cfg(feature = "cjk")]
+use crate::{CjkDecoded, CjkEncoding};
+use crate::{
+	DecodeError, DecodedChar, SingleByteDecoded, SingleByteEncoding, UnicodeDecoded,
+	UnicodeEncoding,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// Character encoding.
+///
+/// Any of the encodings supported by this crate, which can be resolved from
+/// the labels of the WHATWG Encoding Standard with [`Encoding::for_label`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum Encoding {
+	/// Unicode encoding form.
+	Unicode(UnicodeEncoding),
+
+	/// Single-byte legacy encoding.
+	SingleByte(SingleByteEncoding),
+
+	/// Multi-byte CJK legacy encoding.
+	#[cfg(feature = "cjk")]
+	Cjk(CjkEncoding),
+}
+
+impl Encoding {
+	/// Finds the encoding designated by the given label, as defined by the
+	/// WHATWG Encoding Standard.
+	///
+	/// Leading and trailing ASCII whitespace is ignored, and labels are
+	/// matched ASCII case-insensitively. The non-standard `utf-32`,
+	/// `utf-32le` and `utf-32be` labels are also recognized.
+	///
+	/// Returns `None` if the label is unknown, or if it designates an
+	/// encoding this crate does not decode: ISO-2022-JP, the `replacement`
+	/// encoding, and the CJK encodings without the `cjk` feature. As in the
+	/// standard, the ISO-8859-1 and US-ASCII labels designate Windows-1252,
+	/// ISO-8859-9 designates Windows-1254 and ISO-8859-11 designates
+	/// Windows-874. The ISO-8859-8-I labels designate ISO-8859-8, which is
+	/// decoded identically.
+	pub fn for_label(label: &str) -> Option<Self> {
+		use SingleByteEncoding::*;
+		let label = label
+			.trim_matches(|c| matches!(c, '\t' | '\n' | '\x0c' | '\r' | ' '))
+			.to_ascii_lowercase();
+
+		let single_byte =
+			match label.as_str() {
+				"unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+				| "x-unicode20utf8" => return Some(Self::Unicode(UnicodeEncoding::Utf8)),
+				"csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff"
+				| "utf-16" | "utf-16le" => return Some(Self::Unicode(UnicodeEncoding::Utf16Le)),
+				"unicodefffe" | "utf-16be" => return Some(Self::Unicode(UnicodeEncoding::Utf16Be)),
+				"utf-32" | "utf-32le" => return Some(Self::Unicode(UnicodeEncoding::Utf32Le)),
+				"utf-32be" => return Some(Self::Unicode(UnicodeEncoding::Utf32Be)),
+				"866" | "cp866" | "csibm866" | "ibm866" => Ibm866,
+				"csisolatin2" | "iso-8859-2" | "iso-ir-101" | "iso8859-2" | "iso88592"
+				| "iso_8859-2" | "iso_8859-2:1987" | "l2" | "latin2" => Iso8859_2,
+				"csisolatin3" | "iso-8859-3" | "iso-ir-109" | "iso8859-3" | "iso88593"
+				| "iso_8859-3" | "iso_8859-3:1988" | "l3" | "latin3" => Iso8859_3,
+				"csisolatin4" | "iso-8859-4" | "iso-ir-110" | "iso8859-4" | "iso88594"
+				| "iso_8859-4" | "iso_8859-4:1988" | "l4" | "latin4" => Iso8859_4,
+				"csisolatincyrillic" | "cyrillic" | "iso-8859-5" | "iso-ir-144" | "iso8859-5"
+				| "iso88595" | "iso_8859-5" | "iso_8859-5:1988" => Iso8859_5,
+				"arabic" | "asmo-708" | "csiso88596e" | "csiso88596i" | "csisolatinarabic"
+				| "ecma-114" | "iso-8859-6" | "iso-8859-6-e" | "iso-8859-6-i" | "iso-ir-127"
+				| "iso8859-6" | "iso88596" | "iso_8859-6" | "iso_8859-6:1987" => Iso8859_6,
+				"csisolatingreek" | "ecma-118" | "elot_928" | "greek" | "greek8" | "iso-8859-7"
+				| "iso-ir-126" | "iso8859-7" | "iso88597" | "iso_8859-7" | "iso_8859-7:1987"
+				| "sun_eu_greek" => Iso8859_7,
+				"csiso88598e" | "csisolatinhebrew" | "hebrew" | "iso-8859-8" | "iso-8859-8-e"
+				| "iso-ir-138" | "iso8859-8" | "iso88598" | "iso_8859-8" | "iso_8859-8:1988"
+				| "visual" | "csiso88598i" | "iso-8859-8-i" | "logical" => Iso8859_8,
+				"csisolatin6" | "iso-8859-10" | "iso-ir-157" | "iso8859-10" | "iso885910"
+				| "l6" | "latin6" => Iso8859_10,
+				"iso-8859-13" | "iso8859-13" | "iso885913" => Iso8859_13,
+				"iso-8859-14" | "iso8859-14" | "iso885914" => Iso8859_14,
+				"csisolatin9" | "iso-8859-15" | "iso8859-15" | "iso885915" | "iso_8859-15"
+				| "l9" => Iso8859_15,
+				"iso-8859-16" => Iso8859_16,
+				"cskoi8r" | "koi" | "koi8" | "koi8-r" | "koi8_r" => Koi8R,
+				"koi8-ru" | "koi8-u" => Koi8U,
+				"csmacintosh" | "mac" | "macintosh" | "x-mac-roman" => MacRoman,
+				"dos-874" | "iso-8859-11" | "iso8859-11" | "iso885911" | "tis-620"
+				| "windows-874" => Windows874,
+				"cp1250" | "windows-1250" | "x-cp1250" => Windows1250,
+				"cp1251" | "windows-1251" | "x-cp1251" => Windows1251,
+				"ansi_x3.4-1968" | "ascii" | "cp1252" | "cp819" | "csisolatin1" | "ibm819"
+				| "iso-8859-1" | "iso-ir-100" | "iso8859-1" | "iso88591" | "iso_8859-1"
+				| "iso_8859-1:1987" | "l1" | "latin1" | "us-ascii" | "windows-1252"
+				| "x-cp1252" => Windows1252,
+				"cp1253" | "windows-1253" | "x-cp1253" => Windows1253,
+				"cp1254" | "csisolatin5" | "iso-8859-9" | "iso-ir-148" | "iso8859-9"
+				| "iso88599" | "iso_8859-9" | "iso_8859-9:1989" | "l5" | "latin5"
+				| "windows-1254" | "x-cp1254" => Windows1254,
+				"cp1255" | "windows-1255" | "x-cp1255" => Windows1255,
+				"cp1256" | "windows-1256" | "x-cp1256" => Windows1256,
+				"cp1257" | "windows-1257" | "x-cp1257" => Windows1257,
+				"cp1258" | "windows-1258" | "x-cp1258" => Windows1258,
+				"x-mac-cyrillic" | "x-mac-ukrainian" => MacCyrillic,
+				"x-user-defined" => XUserDefined,
+				label => return Self::for_cjk_label(label),
+			};
+
+		Some(Self::SingleByte(single_byte))
+	}
+
+	/// Finds the CJK encoding designated by the given normalized label.
+	#[cfg(feature = "cjk")]
+	fn for_cjk_label(label: &str) -> Option<Self> {
+		let cjk = match label {
+			"chinese" | "csgb2312" | "csiso58gb231280" | "gb2312" | "gb_2312" | "gb_2312-80"
+			| "gbk" | "iso-ir-58" | "x-gbk" => CjkEncoding::Gbk,
+			"gb18030" => CjkEncoding::Gb18030,
+			"big5" | "big5-hkscs" | "cn-big5" | "csbig5" | "x-x-big5" => CjkEncoding::Big5,
+			"cseucpkdfmtjapanese" | "euc-jp" | "x-euc-jp" => CjkEncoding::EucJp,
+			"csshiftjis" | "ms932" | "ms_kanji" | "shift-jis" | "shift_jis" | "sjis"
+			| "windows-31j" | "x-sjis" => CjkEncoding::ShiftJis,
+			"cseuckr" | "csksc56011987" | "euc-kr" | "iso-ir-149" | "korean" | "ks_c_5601-1987"
+			| "ks_c_5601-1989" | "ksc5601" | "ksc_5601" | "windows-949" => CjkEncoding::EucKr,
+			_ => return None,
+		};
+
+		Some(Self::Cjk(cjk))
+	}
+
+	/// Finds the CJK encoding designated by the given normalized label.
+	#[cfg(not(feature = "cjk"))]
+	#[inline(always)]
+	fn for_cjk_label(_label: &str) -> Option<Self> {
+		None
+	}
+
+	/// Returns the name of the encoding.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Unicode(e) => e.name(),
+			Self::SingleByte(e) => e.name(),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(e) => e.name(),
+		}
+	}
+}
+
+impl From<UnicodeEncoding> for Encoding {
+	#[inline(always)]
+	fn from(e: UnicodeEncoding) -> Self {
+		Self::Unicode(e)
+	}
+}
+
+impl From<SingleByteEncoding> for Encoding {
+	#[inline(always)]
+	fn from(e: SingleByteEncoding) -> Self {
+		Self::SingleByte(e)
+	}
+}
+
+#[cfg(feature = "cjk")]
+impl From<CjkEncoding> for Encoding {
+	#[inline(always)]
+	fn from(e: CjkEncoding) -> Self {
+		Self::Cjk(e)
+	}
+}
+
+impl fmt::Display for Encoding {
+	#[inline(always)]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.name().fmt(f)
+	}
+}
+
+/// Unknown or unsupported encoding label.
+#[derive(Debug)]
+pub struct UnknownEncoding(pub String);
+
+impl fmt::Display for UnknownEncoding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "unknown encoding `{}`", self.0)
+	}
+}
+
+impl std::error::Error for UnknownEncoding {}
+
+impl FromStr for Encoding {
+	type Err = UnknownEncoding;
+
+	/// Parses an encoding label, using [`Encoding::for_label`].
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::for_label(s).ok_or_else(|| UnknownEncoding(s.to_owned()))
+	}
+}
+
+/// Decoder for bytes encoded with any of the encodings supported by this
+/// crate, selected at run time.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum EncodingDecoded<B> {
+	/// Unicode decoder.
+	Unicode(UnicodeDecoded<B>),
+
+	/// Single-byte decoder.
+	SingleByte(SingleByteDecoded<B>),
+
+	/// Multi-byte CJK decoder.
+	#[cfg(feature = "cjk")]
+	Cjk(CjkDecoded<B>),
+}
+
+impl<B> EncodingDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: Encoding) -> Self {
+		Self::with_start(bytes, encoding, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	pub fn with_start(bytes: B, encoding: Encoding, offset: usize) -> Self {
+		match encoding {
+			Encoding::Unicode(e) => Self::Unicode(UnicodeDecoded::with_start(bytes, e, offset)),
+			Encoding::SingleByte(e) => {
+				Self::SingleByte(SingleByteDecoded::with_start(bytes, e, offset))
+			}
+			#[cfg(feature = "cjk")]
+			Encoding::Cjk(e) => Self::Cjk(CjkDecoded::with_start(bytes, e, offset)),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::Unicode(d) => d.offset(),
+			Self::SingleByte(d) => d.offset(),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(d) => d.offset(),
+		}
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for EncodingDecoded<B> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		match self {
+			Self::Unicode(d) => d.next(),
+			Self::SingleByte(d) => d.next().map(|r| r.map_err(Into::into)),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(d) => d.next().map(|r| r.map_err(Into::into)),
+		}
+	}
+}
+
+/// Decodes the given bytes with the given encoding.
+///
+/// The bytes are decoded as they are: a byte order mark is decoded as a
+/// `U+FEFF` character. Use [`BomDecoded`](crate::BomDecoded) to let the byte
+/// order mark select the encoding.
+#[inline(always)]
+pub fn decode<B: IntoIterator<Item = u8>>(
+	encoding: Encoding,
+	bytes: B,
+) -> EncodingDecoded<B::IntoIter> {
+	EncodingDecoded::new(bytes.into_iter(), encoding)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Decodes the given bytes lossily with the encoding of the given label.
+	fn decode_label(label: &str, bytes: &[u8]) -> String {
+		let encoding = Encoding::for_label(label).unwrap();
+		decode(encoding, bytes.iter().copied())
+			.map(|c| c.map_or(char::REPLACEMENT_CHARACTER, |c| c.chr()))
+			.collect()
+	}
+
+	#[test]
+	fn labels() {
+		use SingleByteEncoding::*;
+		let cases = [
+			(" UTF8\n", Encoding::Unicode(UnicodeEncoding::Utf8)),
+			("utf-16", Encoding::Unicode(UnicodeEncoding::Utf16Le)),
+			("Latin1", Encoding::SingleByte(Windows1252)),
+			("us-ascii", Encoding::SingleByte(Windows1252)),
+			("iso-8859-9", Encoding::SingleByte(Windows1254)),
+			("tis-620", Encoding::SingleByte(Windows874)),
+			("iso-8859-8-i", Encoding::SingleByte(Iso8859_8)),
+			("koi8-ru", Encoding::SingleByte(Koi8U)),
+		];
+
+		for (label, encoding) in cases {
+			assert_eq!(Encoding::for_label(label), Some(encoding), "{label}");
+		}
+
+		for label in ["iso-2022-jp", "replacement", "utf-7", ""] {
+			assert_eq!(Encoding::for_label(label), None, "{label}");
+		}
+
+		assert_eq!(
+			"x-sjis".parse::<Encoding>().map(|e| e.name()).ok(),
+			cfg!(feature = "cjk").then_some("Shift_JIS")
+		);
+		assert_eq!(
+			"bogus".parse::<Encoding>().unwrap_err().to_string(),
+			"unknown encoding `bogus`"
+		);
+	}
+
+	#[test]
+	fn decode_labelled() {
+		// C1 bytes decode to C1 control characters.
+		assert_eq!(
+			decode_label("latin1", b"\x80\x81\x8d\x8f\x90\x9d\xe9"),
+			"\u{20ac}\u{81}\u{8d}\u{8f}\u{90}\u{9d}\u{e9}"
+		);
+		assert_eq!(decode_label("ascii", b"a\x81"), "a\u{81}");
+		assert_eq!(decode_label("koi8-ru", b"\xae\xbe"), "\u{45e}\u{40e}");
+	}
+
+	#[test]
+	fn offsets() {
+		let encoding = Encoding::for_label("utf-16be").unwrap();
+		let items: Vec<_> =
+			EncodingDecoded::with_start(b"\x00a\xd8\x00".iter().copied(), encoding, 2)
+				.map(|item| item.map(|c| c.chr()).map_err(|e| e.offset()))
+				.collect();
+		assert_eq!(items, [Ok('a'), Err(4)]);
+	}
+}
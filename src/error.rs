@@ -0,0 +1,138 @@
+use crate::{ByteLen, SourceLen, UnmappedByte, Utf16Error, Utf32Error, Utf8Error};
+use std::fmt;
+use std::io;
+
+/// Error raised while decoding characters.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecodeError {
+	/// The reader failed.
+	Io {
+		/// Byte offset, in the encoded source file, at which the read failed.
+		offset: usize,
+
+		/// I/O error.
+		error: io::Error,
+	},
+
+	/// The input is not valid UTF-8.
+	Utf8(Utf8Error),
+
+	/// The input is not valid UTF-16.
+	Utf16(Utf16Error),
+
+	/// The input is not valid UTF-32.
+	Utf32(Utf32Error),
+
+	/// The input contains a byte that is not mapped by the single-byte
+	/// encoding.
+	UnmappedByte(UnmappedByte),
+
+	/// The input is not valid in the multi-byte CJK encoding.
+	#[cfg(feature = "cjk")]
+	Cjk(crate::CjkError),
+}
+
+impl DecodeError {
+	/// Returns the byte offset, in the encoded source file, at which the
+	/// error occurred.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::Io { offset, .. } => *offset,
+			Self::Utf8(e) => e.offset(),
+			Self::Utf16(e) => e.offset(),
+			Self::Utf32(e) => e.offset(),
+			Self::UnmappedByte(e) => e.offset(),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(e) => e.offset(),
+		}
+	}
+
+	/// Checks if this is an I/O error.
+	#[inline(always)]
+	pub fn is_io(&self) -> bool {
+		matches!(self, Self::Io { .. })
+	}
+}
+
+impl From<Utf8Error> for DecodeError {
+	#[inline(always)]
+	fn from(e: Utf8Error) -> Self {
+		Self::Utf8(e)
+	}
+}
+
+impl From<Utf16Error> for DecodeError {
+	#[inline(always)]
+	fn from(e: Utf16Error) -> Self {
+		Self::Utf16(e)
+	}
+}
+
+impl From<Utf32Error> for DecodeError {
+	#[inline(always)]
+	fn from(e: Utf32Error) -> Self {
+		Self::Utf32(e)
+	}
+}
+
+impl From<UnmappedByte> for DecodeError {
+	#[inline(always)]
+	fn from(e: UnmappedByte) -> Self {
+		Self::UnmappedByte(e)
+	}
+}
+
+#[cfg(feature = "cjk")]
+impl From<crate::CjkError> for DecodeError {
+	#[inline(always)]
+	fn from(e: crate::CjkError) -> Self {
+		Self::Cjk(e)
+	}
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Io { offset, error } => write!(f, "read error at offset {offset}: {error}"),
+			Self::Utf8(e) => e.fmt(f),
+			Self::Utf16(e) => e.fmt(f),
+			Self::Utf32(e) => e.fmt(f),
+			Self::UnmappedByte(e) => e.fmt(f),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(e) => e.fmt(f),
+		}
+	}
+}
+
+impl std::error::Error for DecodeError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io { error, .. } => Some(error),
+			Self::Utf8(e) => Some(e),
+			Self::Utf16(e) => Some(e),
+			Self::Utf32(e) => Some(e),
+			Self::UnmappedByte(e) => Some(e),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(e) => Some(e),
+		}
+	}
+}
+
+/// Only encoding errors cover a part of the source. I/O errors have a length
+/// of 0.
+impl SourceLen<ByteLen> for DecodeError {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		match self {
+			Self::Io { .. } => ByteLen(0),
+			Self::Utf8(e) => e.source_len(),
+			Self::Utf16(e) => e.source_len(),
+			Self::Utf32(e) => e.source_len(),
+			Self::UnmappedByte(e) => e.source_len(),
+			#[cfg(feature = "cjk")]
+			Self::Cjk(e) => e.source_len(),
+		}
+	}
+}
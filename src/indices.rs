@@ -0,0 +1,364 @@
+use crate::{
+	ByteLen, Columns, DecodedChar, Length, PositionTracker, Positions, UnmappedByte, Utf16Error,
+	Utf16Len, Utf32Error, Utf8Error,
+};
+use std::char::DecodeUtf16Error;
+use std::convert::Infallible;
+
+/// Value covering a part of the encoded source file.
+///
+/// This is implemented by decoding errors so that offsets can be kept
+/// consistent after an invalid sequence.
+pub trait SourceLen<L> {
+	/// Returns the length of the source covered by this value.
+	fn source_len(&self) -> L;
+}
+
+impl<L> SourceLen<L> for Infallible {
+	fn source_len(&self) -> L {
+		match *self {}
+	}
+}
+
+impl SourceLen<ByteLen> for Utf8Error {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(self.len())
+	}
+}
+
+impl SourceLen<ByteLen> for Utf16Error {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(self.len())
+	}
+}
+
+impl SourceLen<ByteLen> for Utf32Error {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(self.len())
+	}
+}
+
+impl SourceLen<ByteLen> for UnmappedByte {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(1)
+	}
+}
+
+#[cfg(feature = "cjk")]
+impl SourceLen<ByteLen> for crate::CjkError {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(self.len())
+	}
+}
+
+impl SourceLen<ByteLen> for DecodeUtf16Error {
+	#[inline(always)]
+	fn source_len(&self) -> ByteLen {
+		ByteLen(2)
+	}
+}
+
+impl SourceLen<Utf16Len> for DecodeUtf16Error {
+	#[inline(always)]
+	fn source_len(&self) -> Utf16Len {
+		Utf16Len(1)
+	}
+}
+
+/// Item of an iterator over decoded characters.
+pub trait DecodedItem {
+	/// Length unit.
+	type Len: Length;
+
+	/// Item with some value `T` (such as its offset) attached.
+	type With<T>;
+
+	/// Returns the decoded character, or `None` if this item is an error.
+	fn decoded_char(&self) -> Option<char>;
+
+	/// Returns the length of the source covered by this item.
+	fn source_len(&self) -> Self::Len;
+
+	/// Attaches the given value to this item.
+	fn with<T>(self, value: T) -> Self::With<T>;
+}
+
+impl<L: Length> DecodedItem for DecodedChar<L> {
+	type Len = L;
+	type With<T> = (T, Self);
+
+	#[inline(always)]
+	fn decoded_char(&self) -> Option<char> {
+		Some(self.chr())
+	}
+
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		self.len()
+	}
+
+	#[inline(always)]
+	fn with<T>(self, value: T) -> (T, Self) {
+		(value, self)
+	}
+}
+
+impl<L: Length, E: SourceLen<L>> DecodedItem for Result<DecodedChar<L>, E> {
+	type Len = L;
+	type With<T> = Result<(T, DecodedChar<L>), E>;
+
+	#[inline(always)]
+	fn decoded_char(&self) -> Option<char> {
+		self.as_ref().ok().map(DecodedChar::chr)
+	}
+
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		match self {
+			Ok(c) => c.len(),
+			Err(e) => e.source_len(),
+		}
+	}
+
+	#[inline(always)]
+	fn with<T>(self, value: T) -> Self::With<T> {
+		self.map(|c| (value, c))
+	}
+}
+
+/// Iterator adaptor yielding decoded characters along with their offset in
+/// the encoded source file.
+///
+/// The offset is expressed in the length unit of the decoded characters.
+/// Errors are passed through, and their length (given by [`SourceLen`]) is
+/// taken into account to compute the offset of the following characters.
+///
+/// Characters can only be iterated from the start. Use
+/// [`BoundedCharIndices`] to also iterate from the end.
+#[derive(Clone, Debug)]
+pub struct DecodedCharIndices<I> {
+	/// Decoded characters.
+	iter: I,
+
+	/// Offset of the next character.
+	offset: usize,
+}
+
+impl<I> DecodedCharIndices<I> {
+	#[inline(always)]
+	pub fn new(iter: I) -> Self {
+		Self::with_start(iter, 0)
+	}
+
+	/// Creates a new adaptor where the first character is at the given offset.
+	#[inline(always)]
+	pub fn with_start(iter: I, offset: usize) -> Self {
+		Self { iter, offset }
+	}
+
+	/// Returns the offset of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the underlying iterator.
+	#[inline(always)]
+	pub fn into_inner(self) -> I {
+		self.iter
+	}
+}
+
+impl<I: Iterator> Iterator for DecodedCharIndices<I>
+where
+	I::Item: DecodedItem,
+{
+	type Item = <I::Item as DecodedItem>::With<usize>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(|item| {
+			let offset = self.offset;
+			self.offset += item.source_len().get();
+			item.with(offset)
+		})
+	}
+}
+
+/// Iterator adaptor yielding decoded characters along with their offset in
+/// the encoded source file, from both ends.
+///
+/// Behaves like [`DecodedCharIndices`], but since the end offset of the
+/// source is known, characters can also be iterated from the end.
+#[derive(Clone, Debug)]
+pub struct BoundedCharIndices<I> {
+	/// Decoded characters.
+	iter: I,
+
+	/// Offset of the next character.
+	offset: usize,
+
+	/// Offset of the end of the next character from the end.
+	end: usize,
+}
+
+impl<I> BoundedCharIndices<I> {
+	/// Creates a new adaptor where the first character is at offset `start`
+	/// and the last character ends at offset `end`.
+	#[inline(always)]
+	pub fn new(iter: I, start: usize, end: usize) -> Self {
+		Self {
+			iter,
+			offset: start,
+			end,
+		}
+	}
+
+	/// Returns the offset of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the offset of the end of the next character from the end.
+	#[inline(always)]
+	pub fn end_offset(&self) -> usize {
+		self.end
+	}
+
+	/// Returns the underlying iterator.
+	#[inline(always)]
+	pub fn into_inner(self) -> I {
+		self.iter
+	}
+}
+
+impl<I: Iterator> Iterator for BoundedCharIndices<I>
+where
+	I::Item: DecodedItem,
+{
+	type Item = <I::Item as DecodedItem>::With<usize>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(|item| {
+			let offset = self.offset;
+			self.offset += item.source_len().get();
+			item.with(offset)
+		})
+	}
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for BoundedCharIndices<I>
+where
+	I::Item: DecodedItem,
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.iter.next_back().map(|item| {
+			self.end -= item.source_len().get();
+			item.with(self.end)
+		})
+	}
+}
+
+/// Extension for iterators over decoded characters, providing the
+/// `with_offsets`, `with_positions` and `with_lsp_positions` methods.
+pub trait DecodedIterator: Sized + Iterator
+where
+	Self::Item: DecodedItem,
+{
+	/// Returns an iterator yielding each decoded character along with its
+	/// offset in the encoded source file.
+	#[inline(always)]
+	fn with_offsets(self) -> DecodedCharIndices<Self> {
+		DecodedCharIndices::new(self)
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// position (offset, line and column) in the encoded source file.
+	#[inline(always)]
+	fn with_positions(self) -> Positions<Self> {
+		Positions::new(self, PositionTracker::new())
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// position in the encoded source file, where columns are expressed in
+	/// all the LSP position encodings at once.
+	#[inline(always)]
+	fn with_lsp_positions(self) -> Positions<Self, Columns> {
+		Positions::new(self, PositionTracker::default())
+	}
+}
+
+impl<I: Iterator> DecodedIterator for I where I::Item: DecodedItem {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DecodedBytes, DecodedChars, Utf8BytesDecoded};
+
+	#[test]
+	fn offsets() {
+		let offsets: Vec<_> = "aé😀b"
+			.decoded_chars()
+			.with_offsets()
+			.map(|(offset, c)| (offset, c.chr()))
+			.collect();
+		assert_eq!(offsets, [(0, 'a'), (1, 'é'), (3, '😀'), (7, 'b')]);
+	}
+
+	#[test]
+	fn offsets_after_errors() {
+		let offsets: Vec<_> = Utf8BytesDecoded::new(b"a\xe2\x82b\xffc".iter().copied())
+			.with_offsets()
+			.map(|item| {
+				item.map(|(offset, c)| (offset, c.chr()))
+					.map_err(|e| e.offset())
+			})
+			.collect();
+		assert_eq!(
+			offsets,
+			[Ok((0, 'a')), Err(1), Ok((3, 'b')), Err(4), Ok((5, 'c'))]
+		);
+	}
+
+	#[test]
+	fn start_offset() {
+		let offsets: Vec<_> = DecodedCharIndices::with_start("ab".decoded_chars(), 10)
+			.map(|(offset, _)| offset)
+			.collect();
+		assert_eq!(offsets, [10, 11]);
+	}
+
+	#[test]
+	fn both_ends() {
+		let mut indices = "aé😀b".decoded_char_indices();
+		assert_eq!(
+			indices.next_back().map(|(o, c)| (o, c.chr())),
+			Some((7, 'b'))
+		);
+		assert_eq!(indices.next().map(|(o, c)| (o, c.chr())), Some((0, 'a')));
+		assert_eq!(
+			indices.next_back().map(|(o, c)| (o, c.chr())),
+			Some((3, '😀'))
+		);
+		assert_eq!(indices.offset(), 1);
+		assert_eq!(indices.end_offset(), 3);
+		assert_eq!(indices.next().map(|(o, c)| (o, c.chr())), Some((1, 'é')));
+		assert_eq!(indices.next(), None);
+		assert_eq!(indices.next_back(), None);
+
+		let bytes = b"a\xffb";
+		let backward: Vec<_> = BoundedCharIndices::new(bytes.decoded_utf8_chars(), 0, bytes.len())
+			.rev()
+			.map(|item| item.map(|(offset, _)| offset).map_err(|e| e.offset()))
+			.collect();
+		assert_eq!(backward, [Ok(2), Err(1), Ok(0)]);
+	}
+}
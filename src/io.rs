@@ -0,0 +1,144 @@
+use crate::{DecodeError, DecodedChar, Utf8Stream};
+use std::io::{self, BufRead};
+
+/// Decoder for UTF-8 encoded readers.
+///
+/// Decodes characters directly from the buffer of a [`BufRead`] reader,
+/// without loading the whole input in memory. Sequences split across buffer
+/// boundaries are decoded as if the input was contiguous.
+///
+/// I/O errors are reported but do not end the iteration: the next call to
+/// `next` tries to read again, unless the error kind is
+/// [`io::ErrorKind::Interrupted`], in which case the read is retried
+/// transparently.
+#[derive(Debug)]
+pub struct ReaderDecoded<R> {
+	/// Reader.
+	reader: R,
+
+	/// Decoding state.
+	stream: Utf8Stream,
+}
+
+impl<R> ReaderDecoded<R> {
+	#[inline(always)]
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			stream: Utf8Stream::default(),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.stream.offset()
+	}
+
+	/// Returns a reference to the underlying reader.
+	#[inline(always)]
+	pub fn get_ref(&self) -> &R {
+		&self.reader
+	}
+
+	/// Returns the underlying reader.
+	#[inline(always)]
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+
+impl<R: BufRead> Iterator for ReaderDecoded<R> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let buffer = match self.reader.fill_buf() {
+				Ok(buffer) => buffer,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(error) => {
+					return Some(Err(DecodeError::Io {
+						offset: self.stream.position(),
+						error,
+					}))
+				}
+			};
+
+			if buffer.is_empty() {
+				break self.stream.finish().map(|e| Err(e.into()));
+			}
+
+			let (consumed, item) = self.stream.decode(buffer);
+			self.reader.consume(consumed);
+
+			if let Some(item) = item {
+				break Some(item.map_err(Into::into));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedIterator;
+	use std::io::{BufReader, Read};
+
+	/// Decodes the given bytes read one byte at a time.
+	fn decode(bytes: &[u8]) -> Vec<Result<(usize, char), usize>> {
+		ReaderDecoded::new(BufReader::with_capacity(1, bytes))
+			.with_offsets()
+			.map(|item| {
+				item.map(|(offset, c)| (offset, c.chr()))
+					.map_err(|e| e.offset())
+			})
+			.collect()
+	}
+
+	#[test]
+	fn split_sequences() {
+		assert_eq!(
+			decode("aé😀".as_bytes()),
+			[Ok((0, 'a')), Ok((1, 'é')), Ok((3, '😀'))]
+		);
+		assert_eq!(decode(b"\xe2\x82a\xf0\x9f"), [Err(0), Ok((2, 'a')), Err(3)]);
+	}
+
+	/// Reader failing once after the given number of bytes.
+	struct Failing<'a> {
+		bytes: &'a [u8],
+		fail_at: Option<usize>,
+	}
+
+	impl<'a> Read for Failing<'a> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			if self.fail_at == Some(0) {
+				self.fail_at = None;
+				return Err(io::Error::other("failure"));
+			}
+
+			let n = buf
+				.len()
+				.min(self.bytes.len())
+				.min(self.fail_at.unwrap_or(usize::MAX));
+			buf[..n].copy_from_slice(&self.bytes[..n]);
+			self.bytes = &self.bytes[n..];
+			self.fail_at = self.fail_at.map(|i| i - n);
+			Ok(n)
+		}
+	}
+
+	#[test]
+	fn io_error() {
+		let reader = Failing {
+			bytes: "aé".as_bytes(),
+			fail_at: Some(2),
+		};
+
+		let items: Vec<_> = ReaderDecoded::new(BufReader::new(reader))
+			.map(|item| item.map(|c| c.chr()).map_err(|e| e.offset()))
+			.collect();
+		assert_eq!(items, [Ok('a'), Err(2), Ok('é')]);
+	}
+}
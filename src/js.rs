@@ -0,0 +1,652 @@
+use crate::{ByteLen, DecodedChar, Length, Lookahead, SourceLen};
+use std::fmt;
+
+/// Kind of ECMAScript literal or identifier.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum JsLiteralKind {
+	/// String literal, in sloppy mode.
+	String,
+
+	/// String literal, in strict mode, where legacy octal escape sequences
+	/// (`\1`, `\08`) and `\8`, `\9` are not allowed.
+	StrictString,
+
+	/// Template literal chunk (between `` ` ``, `}` and `${`), whose cooked
+	/// value is decoded.
+	///
+	/// Legacy octal escape sequences are not allowed, and line breaks are
+	/// normalized to line feeds.
+	Template,
+
+	/// Template literal chunk, whose raw value is decoded.
+	///
+	/// Escape sequences are left as they are, but line breaks are normalized
+	/// to line feeds.
+	RawTemplate,
+
+	/// Identifier name, where only `\u` escape sequences are allowed.
+	Identifier,
+}
+
+impl JsLiteralKind {
+	/// Checks if this is a string literal.
+	#[inline(always)]
+	pub fn is_string(&self) -> bool {
+		matches!(self, Self::String | Self::StrictString)
+	}
+
+	/// Checks if this is a template literal chunk.
+	#[inline(always)]
+	pub fn is_template(&self) -> bool {
+		matches!(self, Self::Template | Self::RawTemplate)
+	}
+}
+
+/// Kind of ECMAScript escape error.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum JsEscapeErrorKind {
+	/// The input ended right after a backslash.
+	Truncated,
+
+	/// `\x` escape sequence not followed by two hexadecimal digits.
+	InvalidHexEscape,
+
+	/// `\u` escape sequence not followed by four hexadecimal digits or by
+	/// hexadecimal digits between braces.
+	InvalidUnicodeEscape,
+
+	/// `\u{` escape sequence above `\u{10FFFF}`.
+	OutOfRangeUnicodeEscape,
+
+	/// Escaped surrogate code point that is not part of a surrogate pair.
+	///
+	/// Surrogate pairs are never combined in identifiers.
+	UnpairedSurrogate(u16),
+
+	/// Legacy octal escape sequence (or `\8`, `\9`) in strict mode or in a
+	/// template literal.
+	OctalEscape,
+
+	/// Escape sequence other than `\u` in an identifier.
+	InvalidIdentifierEscape,
+
+	/// Line feed or carriage return in a string literal.
+	UnescapedLineTerminator(char),
+}
+
+impl fmt::Display for JsEscapeErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Truncated => write!(f, "incomplete escape sequence"),
+			Self::InvalidHexEscape => write!(f, "invalid hexadecimal escape sequence"),
+			Self::InvalidUnicodeEscape => write!(f, "invalid unicode escape sequence"),
+			Self::OutOfRangeUnicodeEscape => write!(f, "undefined unicode code point"),
+			Self::UnpairedSurrogate(unit) => write!(f, "unpaired surrogate {unit:#06x}"),
+			Self::OctalEscape => write!(f, "octal escape sequences are not allowed here"),
+			Self::InvalidIdentifierEscape => write!(f, "invalid escape sequence in identifier"),
+			Self::UnescapedLineTerminator(c) => {
+				write!(f, "unescaped line terminator {c:?} in string literal")
+			}
+		}
+	}
+}
+
+/// ECMAScript escape error.
+///
+/// Offsets and lengths are expressed in the length unit of the decoded
+/// characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct JsEscapeError {
+	/// Kind of error.
+	kind: JsEscapeErrorKind,
+
+	/// Offset of the invalid sequence.
+	offset: usize,
+
+	/// Length of the invalid sequence.
+	len: usize,
+}
+
+impl JsEscapeError {
+	/// Returns the kind of error.
+	#[inline(always)]
+	pub fn kind(&self) -> JsEscapeErrorKind {
+		self.kind
+	}
+
+	/// Returns the offset of the invalid sequence.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the length of the invalid sequence.
+	///
+	/// The invalid sequence starts right after the previous character (so it
+	/// includes any preceding line continuation). An invalid escape sequence
+	/// ends right before the first character that cannot be part of it.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+}
+
+impl fmt::Display for JsEscapeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at offset {}", self.kind, self.offset)
+	}
+}
+
+impl std::error::Error for JsEscapeError {}
+
+impl<L: Length> SourceLen<L> for JsEscapeError {
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		L::new(self.len)
+	}
+}
+
+/// Decoder for the content of an ECMAScript string literal (without its
+/// quotes), template literal chunk or identifier name, over already decoded
+/// characters.
+///
+/// Escape sequences are decoded into a single character whose length is the
+/// length of the whole sequence, in the length unit of the input characters.
+/// Escaped surrogate pairs (such as `\uD83D\uDE00`) are decoded into a single
+/// character whose length is the length of both escape sequences. Escaped
+/// surrogate code points that are not part of a pair cannot be decoded, and
+/// are reported as errors.
+///
+/// A line continuation (a backslash followed by a line break) does not
+/// produce any character: its length is added to the length of the next
+/// decoded character (or error), or of the last one at the end of the input.
+/// In template literals, a carriage return followed by a line feed is decoded
+/// as a single line feed.
+///
+/// An input made only of line continuations yields nothing. Once the iterator
+/// is exhausted, [`offset`](Self::offset) is the end of the input in every
+/// case.
+///
+/// Decoding continues after an error, which covers the invalid sequence.
+///
+/// ```
+/// use decoded_char::{DecodedChars, JsLiteralDecoded, JsLiteralKind};
+///
+/// let literal = r"\x41\u{1F600}\uD83D\uDE00\101";
+/// let decoded: Vec<_> = JsLiteralDecoded::new(literal.decoded_chars(), JsLiteralKind::String)
+///     .map(|c| c.map(|c| (c.chr(), c.len().0)))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(decoded, [('A', 4), ('😀', 9), ('😀', 12), ('A', 4)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct JsLiteralDecoded<C: Iterator<Item = DecodedChar<L>>, L = ByteLen> {
+	/// Decoded characters of the literal.
+	chars: Lookahead<C>,
+
+	/// Kind of literal.
+	kind: JsLiteralKind,
+
+	/// Offset of the first character.
+	start: usize,
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> JsLiteralDecoded<C, L> {
+	#[inline(always)]
+	pub fn new(chars: C, kind: JsLiteralKind) -> Self {
+		Self::with_start(chars, kind, 0)
+	}
+
+	/// Creates a new decoder where the first character is at the given offset
+	/// in the source file.
+	#[inline(always)]
+	pub fn with_start(chars: C, kind: JsLiteralKind, offset: usize) -> Self {
+		Self {
+			chars: Lookahead::new(chars),
+			kind,
+			start: offset,
+		}
+	}
+
+	/// Returns the kind of literal.
+	#[inline(always)]
+	pub fn kind(&self) -> JsLiteralKind {
+		self.kind
+	}
+
+	/// Returns the offset, in the source file, of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.start + self.chars.offset()
+	}
+
+	/// Creates an error covering the source from the given offset to the
+	/// current offset.
+	#[inline(always)]
+	fn error(&self, kind: JsEscapeErrorKind, offset: usize) -> JsEscapeError {
+		JsEscapeError {
+			kind,
+			offset,
+			len: self.offset() - offset,
+		}
+	}
+
+	/// Returns the next character, without consuming it.
+	#[inline(always)]
+	fn peek(&mut self) -> Option<char> {
+		self.chars.peek().map(DecodedChar::chr)
+	}
+
+	/// Consumes the next character if it satisfies the given predicate.
+	#[inline(always)]
+	fn next_if(&mut self, f: impl FnOnce(char) -> bool) -> Option<char> {
+		let c = self.peek().filter(|c| f(*c))?;
+		self.chars.next();
+		Some(c)
+	}
+
+	/// Skips a line continuation, once its backslash is consumed.
+	///
+	/// Returns `false`, without consuming anything, if the backslash does not
+	/// start a line continuation.
+	fn continuation(&mut self) -> bool {
+		if self.kind == JsLiteralKind::Identifier {
+			return false;
+		}
+
+		match self.peek() {
+			Some('\n' | '\u{2028}' | '\u{2029}') => {
+				self.chars.next();
+			}
+			Some('\r') => {
+				self.chars.next();
+				self.next_if(|c| c == '\n');
+			}
+			_ => return false,
+		}
+
+		true
+	}
+
+	/// Skips the line continuations ending the input, if any, so that their
+	/// length is added to the last decoded character.
+	fn trailing_continuations(&mut self) {
+		if self.kind == JsLiteralKind::RawTemplate || self.peek() != Some('\\') {
+			return;
+		}
+
+		let checkpoint = self.chars.checkpoint();
+		loop {
+			match self.chars.next().map(|c| c.chr()) {
+				None => break self.chars.commit(checkpoint),
+				Some('\\') if self.continuation() => (),
+				Some(_) => break self.chars.restore(checkpoint),
+			}
+		}
+	}
+
+	/// Decodes an escape sequence, once its backslash is consumed.
+	///
+	/// Returns `None` for a line continuation.
+	fn escape(&mut self) -> Option<Result<char, JsEscapeErrorKind>> {
+		if self.continuation() {
+			return None;
+		}
+
+		let Some(e) = self.chars.next().map(|e| e.chr()) else {
+			return Some(Err(JsEscapeErrorKind::Truncated));
+		};
+
+		if self.kind == JsLiteralKind::Identifier && e != 'u' {
+			return Some(Err(JsEscapeErrorKind::InvalidIdentifierEscape));
+		}
+
+		let c = match e {
+			'b' => '\u{8}',
+			't' => '\t',
+			'n' => '\n',
+			'v' => '\u{b}',
+			'f' => '\u{c}',
+			'r' => '\r',
+			'x' => return Some(self.hex_escape()),
+			'u' => return Some(self.unicode_escape()),
+			'0'..='9' => return Some(self.octal_escape(e)),
+			c => c,
+		};
+
+		Some(Ok(c))
+	}
+
+	/// Decodes a `\x` escape sequence, once its `\x` prefix is consumed.
+	fn hex_escape(&mut self) -> Result<char, JsEscapeErrorKind> {
+		let mut value = 0;
+		for _ in 0..2 {
+			match self.next_if(|c| c.is_ascii_hexdigit()) {
+				Some(c) => value = value * 16 + c.to_digit(16).unwrap(),
+				None => return Err(JsEscapeErrorKind::InvalidHexEscape),
+			}
+		}
+
+		Ok(char::from(value as u8))
+	}
+
+	/// Decodes the code point of a `\u` escape sequence, once its `\u` prefix
+	/// is consumed.
+	fn code_point(&mut self) -> Result<u32, JsEscapeErrorKind> {
+		let mut value: u32 = 0;
+		if self.next_if(|c| c == '{').is_some() {
+			let mut digits = 0;
+			while let Some(c) = self.next_if(|c| c.is_ascii_hexdigit()) {
+				value = value
+					.saturating_mul(16)
+					.saturating_add(c.to_digit(16).unwrap());
+				digits += 1
+			}
+
+			if digits == 0 || self.next_if(|c| c == '}').is_none() {
+				return Err(JsEscapeErrorKind::InvalidUnicodeEscape);
+			}
+
+			if value > 0x10ffff {
+				return Err(JsEscapeErrorKind::OutOfRangeUnicodeEscape);
+			}
+		} else {
+			for _ in 0..4 {
+				match self.next_if(|c| c.is_ascii_hexdigit()) {
+					Some(c) => value = value * 16 + c.to_digit(16).unwrap(),
+					None => return Err(JsEscapeErrorKind::InvalidUnicodeEscape),
+				}
+			}
+		}
+
+		Ok(value)
+	}
+
+	/// Decodes a `\u` escape sequence, once its `\u` prefix is consumed,
+	/// combining it with the following `\u` escape sequence if they form a
+	/// surrogate pair.
+	fn unicode_escape(&mut self) -> Result<char, JsEscapeErrorKind> {
+		let value = self.code_point()?;
+
+		if (0xd800..0xdc00).contains(&value) && self.kind != JsLiteralKind::Identifier {
+			let checkpoint = self.chars.checkpoint();
+			let low =
+				if self.next_if(|c| c == '\\').is_some() && self.next_if(|c| c == 'u').is_some() {
+					self.code_point().ok()
+				} else {
+					None
+				};
+
+			match low.filter(|low| (0xdc00..0xe000).contains(low)) {
+				Some(low) => {
+					self.chars.commit(checkpoint);
+					return Ok(
+						char::from_u32(0x10000 + ((value - 0xd800) << 10) + (low - 0xdc00))
+							.unwrap(),
+					);
+				}
+				None => self.chars.restore(checkpoint),
+			}
+		}
+
+		char::from_u32(value).ok_or(JsEscapeErrorKind::UnpairedSurrogate(value as u16))
+	}
+
+	/// Decodes an escape sequence starting with the given decimal digit, once
+	/// the digit is consumed.
+	///
+	/// Except for `\0` (not followed by a decimal digit), these are legacy
+	/// octal escape sequences, only allowed in sloppy mode string literals.
+	fn octal_escape(&mut self, first: char) -> Result<char, JsEscapeErrorKind> {
+		let next_digit = self.peek().filter(char::is_ascii_digit);
+		if first == '0' && next_digit.is_none() {
+			return Ok('\0');
+		}
+
+		let mut value = first.to_digit(8);
+		if let Some(mut v) = value {
+			let max_digits = if v <= 3 { 3 } else { 2 };
+			for _ in 1..max_digits {
+				match self.next_if(|c| matches!(c, '0'..='7')) {
+					Some(c) => v = v * 8 + c.to_digit(8).unwrap(),
+					None => break,
+				}
+			}
+
+			value = Some(v)
+		}
+
+		match value {
+			_ if self.kind != JsLiteralKind::String => Err(JsEscapeErrorKind::OctalEscape),
+			Some(value) => Ok(char::from(value as u8)),
+			None => Ok(first),
+		}
+	}
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> Iterator for JsLiteralDecoded<C, L> {
+	type Item = Result<DecodedChar<L>, JsEscapeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let start = self.offset();
+		let result = loop {
+			let c = self.chars.next()?.chr();
+			break match c {
+				'\\' if self.kind != JsLiteralKind::RawTemplate => match self.escape() {
+					Some(result) => result,
+					None => continue,
+				},
+				'\r' if self.kind.is_template() => {
+					self.next_if(|c| c == '\n');
+					Ok('\n')
+				}
+				'\n' | '\r' if self.kind.is_string() => {
+					Err(JsEscapeErrorKind::UnescapedLineTerminator(c))
+				}
+				c => Ok(c),
+			};
+		};
+
+		self.trailing_continuations();
+		Some(match result {
+			Ok(c) => Ok(DecodedChar::new(c, L::new(self.offset() - start))),
+			Err(kind) => Err(self.error(kind, start)),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedChars;
+	use JsEscapeErrorKind::*;
+	use JsLiteralKind::*;
+
+	type Item = Result<(char, usize), (JsEscapeErrorKind, usize, usize)>;
+
+	/// Decodes the given input, checking that items cover the whole input
+	/// without gaps.
+	fn decode(input: &str, kind: JsLiteralKind) -> Vec<Item> {
+		let mut decoder = JsLiteralDecoded::with_start(input.decoded_chars(), kind, 1);
+		let mut items = Vec::new();
+		let mut offset = 1;
+		while let Some(item) = decoder.next() {
+			let item = item
+				.map(|c| (c.chr(), c.len().0))
+				.map_err(|e| (e.kind(), e.offset(), e.len()));
+			let len = match item {
+				Ok((_, len)) => len,
+				Err((_, error_offset, len)) => {
+					assert_eq!(error_offset, offset, "{input:?}");
+					len
+				}
+			};
+
+			offset += len;
+			assert_eq!(decoder.offset(), offset, "{input:?}");
+			items.push(item);
+		}
+
+		assert_eq!(decoder.offset(), 1 + input.len(), "{input:?}");
+		if !items.is_empty() {
+			assert_eq!(offset, 1 + input.len(), "{input:?}");
+		}
+
+		items
+	}
+
+	#[test]
+	fn escapes() {
+		assert_eq!(
+			decode(r"\b\t\n\v\f\r\q\'\0\101\08\8", String),
+			[
+				Ok(('\u{8}', 2)),
+				Ok(('\t', 2)),
+				Ok(('\n', 2)),
+				Ok(('\u{b}', 2)),
+				Ok(('\u{c}', 2)),
+				Ok(('\r', 2)),
+				Ok(('q', 2)),
+				Ok(('\'', 2)),
+				Ok(('\0', 2)),
+				Ok(('A', 4)),
+				Ok(('\0', 2)),
+				Ok(('8', 1)),
+				Ok(('8', 2)),
+			]
+		);
+		assert_eq!(
+			decode(r"\x41\u0041\u{1F600}\uD83D\uDE00", String),
+			[Ok(('A', 4)), Ok(('A', 6)), Ok(('😀', 9)), Ok(('😀', 12))]
+		);
+	}
+
+	#[test]
+	fn kinds() {
+		assert_eq!(
+			decode(r"\1\08\0", StrictString),
+			[
+				Err((OctalEscape, 1, 2)),
+				Err((OctalEscape, 3, 2)),
+				Ok(('8', 1)),
+				Ok(('\0', 2)),
+			]
+		);
+		assert_eq!(decode(r"\8", Template), [Err((OctalEscape, 1, 2))]);
+		assert_eq!(
+			decode("a\r\nb\rc", Template),
+			[
+				Ok(('a', 1)),
+				Ok(('\n', 2)),
+				Ok(('b', 1)),
+				Ok(('\n', 1)),
+				Ok(('c', 1)),
+			]
+		);
+		assert_eq!(
+			decode("\\n\r\n", RawTemplate),
+			[Ok(('\\', 1)), Ok(('n', 1)), Ok(('\n', 2))]
+		);
+		assert_eq!(
+			decode("a\nb\r", String),
+			[
+				Ok(('a', 1)),
+				Err((UnescapedLineTerminator('\n'), 2, 1)),
+				Ok(('b', 1)),
+				Err((UnescapedLineTerminator('\r'), 4, 1)),
+			]
+		);
+
+		// Surrogate pairs are not combined in identifiers.
+		assert_eq!(
+			decode(r"\u0061\uD83D\uDE00\n", Identifier),
+			[
+				Ok(('a', 6)),
+				Err((UnpairedSurrogate(0xd83d), 7, 6)),
+				Err((UnpairedSurrogate(0xde00), 13, 6)),
+				Err((InvalidIdentifierEscape, 19, 2)),
+			]
+		);
+	}
+
+	#[test]
+	fn continuations() {
+		assert_eq!(decode("a\\\nb", String), [Ok(('a', 1)), Ok(('b', 3))]);
+		assert_eq!(
+			decode("a\\\r\n\\\u{2028}b", String),
+			[Ok(('a', 1)), Ok(('b', 8))]
+		);
+		// Trailing continuations are added to the last character.
+		assert_eq!(decode("a\\\r\n\\\n", Template), [Ok(('a', 6))]);
+		// An escaped backslash does not start a line continuation.
+		assert_eq!(
+			decode("\\x\\\\\n", String),
+			[
+				Err((InvalidHexEscape, 1, 2)),
+				Ok(('\\', 2)),
+				Err((UnescapedLineTerminator('\n'), 5, 1)),
+			]
+		);
+
+		// The length of an input made only of line continuations is only
+		// given by the final offset.
+		assert_eq!(decode("\\\n", String), []);
+	}
+
+	#[test]
+	fn errors() {
+		// Errors cover the line continuations before them.
+		assert_eq!(decode("\\\n\\x", String), [Err((InvalidHexEscape, 1, 4))]);
+		assert_eq!(
+			decode("\\\n\\uD83Dx", String),
+			[Err((UnpairedSurrogate(0xd83d), 1, 8)), Ok(('x', 1))]
+		);
+
+		let cases = [
+			(r"\x4", InvalidHexEscape, 3),
+			(r"\x4g", InvalidHexEscape, 3),
+			(r"\u12", InvalidUnicodeEscape, 4),
+			(r"\u{}", InvalidUnicodeEscape, 3),
+			(r"\u{41", InvalidUnicodeEscape, 5),
+			(r"\u{110000}", OutOfRangeUnicodeEscape, 10),
+			(r"\uDE00", UnpairedSurrogate(0xde00), 6),
+			("\\", Truncated, 1),
+		];
+
+		for (input, kind, len) in cases {
+			assert_eq!(decode(input, String)[0], Err((kind, 1, len)), "{input:?}");
+		}
+
+		assert_eq!(
+			decode(r"\uD83D\u0041", String),
+			[Err((UnpairedSurrogate(0xd83d), 1, 6)), Ok(('A', 6))]
+		);
+
+		let e = JsLiteralDecoded::new(r"\x".decoded_chars(), String)
+			.next()
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(
+			e.to_string(),
+			"invalid hexadecimal escape sequence at offset 0"
+		);
+	}
+
+	#[test]
+	fn lengths_add_up() {
+		let inputs = [
+			"plain",
+			"\\\n\\x\\\r\n\\u{41}\\\u{2029}",
+			"é\\uD800\r\\\r\\",
+			"\\u{1F600\\x7f\\12\\8\\\n\n\\\n",
+			"\\\r\n\\\u{2028}",
+		];
+
+		for input in inputs {
+			for kind in [String, StrictString, Template, RawTemplate, Identifier] {
+				decode(input, kind);
+			}
+		}
+	}
+}
@@ -0,0 +1,380 @@
+use crate::{ByteLen, DecodedChar, Length, Lookahead, SourceLen};
+
+/// JSON string escape decoding error.
+///
+/// Offsets and lengths are expressed in the length unit of the decoded
+/// characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum JsonEscapeError {
+	/// Character that must be escaped in a JSON string: quotation mark or
+	/// control character (`U+0000` to `U+001F`).
+	Unescaped {
+		/// Offset of the character.
+		offset: usize,
+
+		/// Length of the character.
+		len: usize,
+
+		/// Character.
+		c: char,
+	},
+
+	/// Backslash followed by a character that does not start an escape
+	/// sequence.
+	InvalidEscape {
+		/// Offset of the backslash.
+		offset: usize,
+
+		/// Length of the backslash and the following character.
+		len: usize,
+
+		/// Character following the backslash.
+		c: char,
+	},
+
+	/// `\u` escape sequence not followed by four hexadecimal digits.
+	InvalidUnicodeEscape {
+		/// Offset of the backslash.
+		offset: usize,
+
+		/// Length of the escape sequence, up to the first character that is
+		/// not a hexadecimal digit.
+		len: usize,
+	},
+
+	/// `\u` escape sequence designating a surrogate code unit that is not part
+	/// of a surrogate pair.
+	UnpairedSurrogate {
+		/// Offset of the backslash.
+		offset: usize,
+
+		/// Length of the escape sequence.
+		len: usize,
+
+		/// Surrogate code unit.
+		unit: u16,
+	},
+
+	/// The string ended right after a backslash.
+	Truncated {
+		/// Offset of the backslash.
+		offset: usize,
+
+		/// Length of the backslash.
+		len: usize,
+	},
+}
+
+impl JsonEscapeError {
+	/// Returns the offset of the invalid sequence.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::Unescaped { offset, .. }
+			| Self::InvalidEscape { offset, .. }
+			| Self::InvalidUnicodeEscape { offset, .. }
+			| Self::UnpairedSurrogate { offset, .. }
+			| Self::Truncated { offset, .. } => *offset,
+		}
+	}
+
+	/// Returns the length of the invalid sequence.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Unescaped { len, .. }
+			| Self::InvalidEscape { len, .. }
+			| Self::InvalidUnicodeEscape { len, .. }
+			| Self::UnpairedSurrogate { len, .. }
+			| Self::Truncated { len, .. } => *len,
+		}
+	}
+}
+
+impl std::fmt::Display for JsonEscapeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Unescaped { offset, c, .. } => {
+				write!(f, "unescaped character {c:?} at offset {offset}")
+			}
+			Self::InvalidEscape { offset, c, .. } => {
+				write!(f, "invalid escape sequence `\\{c}` at offset {offset}")
+			}
+			Self::InvalidUnicodeEscape { offset, .. } => {
+				write!(f, "invalid unicode escape sequence at offset {offset}")
+			}
+			Self::UnpairedSurrogate { offset, unit, .. } => {
+				write!(f, "unpaired surrogate {unit:#06x} at offset {offset}")
+			}
+			Self::Truncated { offset, .. } => {
+				write!(f, "incomplete escape sequence at offset {offset}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for JsonEscapeError {}
+
+impl<L: Length> SourceLen<L> for JsonEscapeError {
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		L::new(self.len())
+	}
+}
+
+/// Decoder for the body of a JSON string literal (without its quotation
+/// marks), over already decoded characters.
+///
+/// Escape sequences are decoded into a single character whose length is the
+/// length of the whole sequence, so that `\n` has a length of 2, `\u00e9` a
+/// length of 6, and an escaped surrogate pair such as `\ud83d\ude00` a
+/// length of 12, in the length unit of the input characters. Other characters
+/// keep their length.
+///
+/// Decoding continues after an error, which covers the invalid sequence.
+///
+/// ```
+/// use decoded_char::{DecodedChars, JsonStringDecoded};
+///
+/// let decoded: Vec<_> = JsonStringDecoded::new(r"a\n\u00e9\ud83d\ude00".decoded_chars())
+///     .map(|c| c.map(|c| (c.chr(), c.len().0)))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(decoded, [('a', 1), ('\n', 2), ('é', 6), ('😀', 12)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct JsonStringDecoded<C: Iterator<Item = DecodedChar<L>>, L = ByteLen> {
+	/// Decoded characters of the literal.
+	chars: Lookahead<C>,
+
+	/// Offset of the first character.
+	start: usize,
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> JsonStringDecoded<C, L> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self::with_start(chars, 0)
+	}
+
+	/// Creates a new decoder where the first character is at the given offset
+	/// in the source file.
+	#[inline(always)]
+	pub fn with_start(chars: C, offset: usize) -> Self {
+		Self {
+			chars: Lookahead::new(chars),
+			start: offset,
+		}
+	}
+
+	/// Returns the offset, in the source file, of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.start + self.chars.offset()
+	}
+
+	/// Peeks the `count` hexadecimal digits starting at the `n`-th next
+	/// character, and returns their value and total length.
+	fn peek_hex(&mut self, n: usize, count: usize) -> Option<(u32, L)> {
+		let mut value = 0;
+		let mut len = L::default();
+		for i in n..(n + count) {
+			let c = self.chars.peek_nth(i)?;
+			value = value * 16 + c.chr().to_digit(16)?;
+			len += c.len();
+		}
+
+		Some((value, len))
+	}
+
+	/// Decodes an escape sequence, once its backslash of the given length is
+	/// consumed.
+	fn escape(&mut self, offset: usize, mut len: L) -> Result<DecodedChar<L>, JsonEscapeError> {
+		let Some(e) = self.chars.next() else {
+			return Err(JsonEscapeError::Truncated {
+				offset,
+				len: len.get(),
+			});
+		};
+
+		len += e.len();
+		let c = match e.chr() {
+			'"' => '"',
+			'\\' => '\\',
+			'/' => '/',
+			'b' => '\u{8}',
+			'f' => '\u{c}',
+			'n' => '\n',
+			'r' => '\r',
+			't' => '\t',
+			'u' => return self.unicode_escape(offset, len),
+			c => {
+				return Err(JsonEscapeError::InvalidEscape {
+					offset,
+					len: len.get(),
+					c,
+				})
+			}
+		};
+
+		Ok(DecodedChar::new(c, len))
+	}
+
+	/// Decodes a `\u` escape sequence, once its `\u` prefix of the given
+	/// length is consumed.
+	fn unicode_escape(
+		&mut self,
+		offset: usize,
+		mut len: L,
+	) -> Result<DecodedChar<L>, JsonEscapeError> {
+		let Some((unit, digits_len)) = self.peek_hex(0, 4) else {
+			while let Some(c) = self.chars.peek().filter(|c| c.chr().is_ascii_hexdigit()) {
+				len += c.len();
+				self.chars.next();
+			}
+
+			return Err(JsonEscapeError::InvalidUnicodeEscape {
+				offset,
+				len: len.get(),
+			});
+		};
+
+		for _ in 0..4 {
+			self.chars.next();
+		}
+		len += digits_len;
+
+		if (0xd800..0xdc00).contains(&unit) {
+			let paired = self.chars.peek_nth(0).map(DecodedChar::chr) == Some('\\')
+				&& self.chars.peek_nth(1).map(DecodedChar::chr) == Some('u');
+			if paired {
+				if let Some((low, low_len)) = self
+					.peek_hex(2, 4)
+					.filter(|(low, _)| (0xdc00..0xe000).contains(low))
+				{
+					len += self.chars.next().unwrap().len() + self.chars.next().unwrap().len();
+					for _ in 0..4 {
+						self.chars.next();
+					}
+
+					let c =
+						char::from_u32(0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)).unwrap();
+					return Ok(DecodedChar::new(c, len + low_len));
+				}
+			}
+		}
+
+		char::from_u32(unit)
+			.map(|c| DecodedChar::new(c, len))
+			.ok_or(JsonEscapeError::UnpairedSurrogate {
+				offset,
+				len: len.get(),
+				unit: unit as u16,
+			})
+	}
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> Iterator for JsonStringDecoded<C, L> {
+	type Item = Result<DecodedChar<L>, JsonEscapeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let offset = self.offset();
+		let c = self.chars.next()?;
+		match c.chr() {
+			'\\' => Some(self.escape(offset, c.len())),
+			'"' | '\0'..='\u{1f}' => Some(Err(JsonEscapeError::Unescaped {
+				offset,
+				len: c.len().get(),
+				c: c.chr(),
+			})),
+			_ => Some(Ok(c)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedChars;
+
+	type Item = Result<(char, usize), (usize, usize)>;
+
+	/// Decodes the given string body, checking that items cover the whole
+	/// input without gaps.
+	fn decode(input: &str) -> Vec<Item> {
+		let mut decoder = JsonStringDecoded::with_start(input.decoded_chars(), 1);
+		let mut items = Vec::new();
+		let mut offset = 1;
+		while let Some(item) = decoder.next() {
+			let len = match &item {
+				Ok(c) => c.len().0,
+				Err(e) => {
+					assert_eq!(e.offset(), offset, "{input:?}");
+					e.len()
+				}
+			};
+
+			offset += len;
+			assert_eq!(decoder.offset(), offset, "{input:?}");
+			items.push(
+				item.map(|c| (c.chr(), c.len().0))
+					.map_err(|e| (e.offset(), e.len())),
+			);
+		}
+
+		assert_eq!(offset, 1 + input.len(), "{input:?}");
+		items
+	}
+
+	#[test]
+	fn escapes() {
+		assert_eq!(
+			decode(r#"\"\\\/\b\f\n\r\t\u00e9"#),
+			[
+				Ok(('"', 2)),
+				Ok(('\\', 2)),
+				Ok(('/', 2)),
+				Ok(('\u{8}', 2)),
+				Ok(('\u{c}', 2)),
+				Ok(('\n', 2)),
+				Ok(('\r', 2)),
+				Ok(('\t', 2)),
+				Ok(('é', 6)),
+			]
+		);
+		assert_eq!(
+			decode(r"\u00e9\ud83d\ude00"),
+			[Ok(('é', 6)), Ok(('😀', 12))]
+		);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(decode("a\"\n"), [Ok(('a', 1)), Err((2, 1)), Err((3, 1))]);
+		assert_eq!(decode(r"\q"), [Err((1, 2))]);
+		assert_eq!(decode(r"\u12x"), [Err((1, 4)), Ok(('x', 1))]);
+		assert_eq!(decode(r"\ud83dx"), [Err((1, 6)), Ok(('x', 1))]);
+		assert_eq!(decode(r"\ud83d\u0041"), [Err((1, 6)), Ok(('A', 6))]);
+		assert_eq!(decode(r"\ude00"), [Err((1, 6))]);
+		assert_eq!(decode(r"a\"), [Ok(('a', 1)), Err((2, 1))]);
+
+		let e = JsonStringDecoded::new(r"\ud83d".decoded_chars())
+			.next()
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(e.to_string(), "unpaired surrogate 0xd83d at offset 0");
+	}
+
+	#[test]
+	fn lengths_add_up() {
+		for input in [
+			r"plain",
+			r#"\u00e9"\\\x\u12\ud800\udc00\ude00"#,
+			"tab\there\\u00zz\\",
+			"é\\ud83d\\ud83d\\ude00\u{1}",
+		] {
+			decode(input);
+		}
+	}
+}
@@ -0,0 +1,267 @@
+use crate::UnicodeEncoding;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Unit in which the length of a [`DecodedChar`](crate::DecodedChar) is
+/// expressed.
+///
+/// Lengths of the same unit can be added to or subtracted from each other,
+/// but lengths of different units cannot be mixed without an explicit
+/// conversion.
+pub trait Length:
+	Copy
+	+ Ord
+	+ Default
+	+ fmt::Debug
+	+ Add<Output = Self>
+	+ AddAssign
+	+ Sub<Output = Self>
+	+ SubAssign
+	+ Sum
+{
+	/// Creates a length from a raw number of units.
+	fn new(n: usize) -> Self;
+
+	/// Returns the length, in this unit, of the given character in an UTF-16
+	/// encoded source file.
+	fn of_utf16(c: char) -> Self;
+
+	/// Returns the raw number of units.
+	fn get(self) -> usize;
+}
+
+macro_rules! length {
+	($($(#[$meta:meta])* $id:ident),*) => {
+		$(
+			$(#[$meta])*
+			#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+			pub struct $id(pub usize);
+
+			impl From<$id> for usize {
+				#[inline(always)]
+				fn from(len: $id) -> Self {
+					len.0
+				}
+			}
+
+			impl Add for $id {
+				type Output = Self;
+
+				#[inline(always)]
+				fn add(self, other: Self) -> Self {
+					Self(self.0 + other.0)
+				}
+			}
+
+			impl AddAssign for $id {
+				#[inline(always)]
+				fn add_assign(&mut self, other: Self) {
+					self.0 += other.0
+				}
+			}
+
+			impl Sub for $id {
+				type Output = Self;
+
+				#[inline(always)]
+				fn sub(self, other: Self) -> Self {
+					Self(self.0 - other.0)
+				}
+			}
+
+			impl SubAssign for $id {
+				#[inline(always)]
+				fn sub_assign(&mut self, other: Self) {
+					self.0 -= other.0
+				}
+			}
+
+			impl Sum for $id {
+				#[inline(always)]
+				fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+					Self(iter.map(|len| len.0).sum())
+				}
+			}
+
+			impl fmt::Display for $id {
+				#[inline(always)]
+				fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+					self.0.fmt(f)
+				}
+			}
+		)*
+	};
+}
+
+length! {
+	/// Length in bytes.
+	ByteLen,
+
+	/// Length in UTF-16 code units.
+	Utf16Len,
+
+	/// Length in Unicode scalar values (characters).
+	///
+	/// This is also the length in UTF-32 code units.
+	CharLen,
+
+	/// Length in code units of the Unicode encoding form of the source file:
+	/// bytes for UTF-8, 16-bit code units for UTF-16 and 32-bit code units
+	/// for UTF-32.
+	CodeUnitLen
+}
+
+impl Length for ByteLen {
+	#[inline(always)]
+	fn new(n: usize) -> Self {
+		Self(n)
+	}
+
+	#[inline(always)]
+	fn of_utf16(c: char) -> Self {
+		Self(c.len_utf16() * 2)
+	}
+
+	#[inline(always)]
+	fn get(self) -> usize {
+		self.0
+	}
+}
+
+impl Length for Utf16Len {
+	#[inline(always)]
+	fn new(n: usize) -> Self {
+		Self(n)
+	}
+
+	#[inline(always)]
+	fn of_utf16(c: char) -> Self {
+		Self(c.len_utf16())
+	}
+
+	#[inline(always)]
+	fn get(self) -> usize {
+		self.0
+	}
+}
+
+impl Utf16Len {
+	/// Returns the byte length of these UTF-16 code units.
+	#[inline(always)]
+	pub fn to_byte_len(self) -> ByteLen {
+		ByteLen(self.0 * 2)
+	}
+}
+
+impl Length for CharLen {
+	#[inline(always)]
+	fn new(n: usize) -> Self {
+		Self(n)
+	}
+
+	#[inline(always)]
+	fn of_utf16(_c: char) -> Self {
+		Self(1)
+	}
+
+	#[inline(always)]
+	fn get(self) -> usize {
+		self.0
+	}
+}
+
+impl Length for CodeUnitLen {
+	#[inline(always)]
+	fn new(n: usize) -> Self {
+		Self(n)
+	}
+
+	#[inline(always)]
+	fn of_utf16(c: char) -> Self {
+		Self(c.len_utf16())
+	}
+
+	#[inline(always)]
+	fn get(self) -> usize {
+		self.0
+	}
+}
+
+impl CodeUnitLen {
+	/// Returns the byte length of these code units, in the given encoding
+	/// form.
+	#[inline(always)]
+	pub fn to_byte_len(self, encoding: UnicodeEncoding) -> ByteLen {
+		ByteLen(self.0 * encoding.code_unit_size())
+	}
+}
+
+impl From<Utf16Len> for CodeUnitLen {
+	/// Converts a length in UTF-16 code units, which are the code units of an
+	/// UTF-16 encoded source file.
+	#[inline(always)]
+	fn from(len: Utf16Len) -> Self {
+		Self(len.0)
+	}
+}
+
+impl From<CharLen> for CodeUnitLen {
+	/// Converts a length in characters, which are the code units of an UTF-32
+	/// encoded source file.
+	#[inline(always)]
+	fn from(len: CharLen) -> Self {
+		Self(len.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DecodedChar, DecodedChars};
+
+	#[test]
+	fn code_unit_len() {
+		let c = DecodedChar::from_utf8('😀');
+		assert_eq!(
+			c.to_code_unit_len(UnicodeEncoding::Utf8).len(),
+			CodeUnitLen(4)
+		);
+		assert_eq!(
+			c.to_code_unit_len(UnicodeEncoding::Utf16Be).len(),
+			CodeUnitLen(2)
+		);
+		assert_eq!(
+			c.to_code_unit_len(UnicodeEncoding::Utf32Le).len(),
+			CodeUnitLen(1)
+		);
+		assert_eq!(
+			CodeUnitLen(2).to_byte_len(UnicodeEncoding::Utf16Le),
+			ByteLen(4)
+		);
+		assert_eq!(
+			CodeUnitLen(2).to_byte_len(UnicodeEncoding::Utf32Be),
+			ByteLen(8)
+		);
+	}
+
+	#[test]
+	fn conversions() {
+		assert_eq!(CodeUnitLen::from(Utf16Len(3)), CodeUnitLen(3));
+		assert_eq!(CodeUnitLen::from(CharLen(3)), CodeUnitLen(3));
+		assert_eq!(Utf16Len(3).to_byte_len(), ByteLen(6));
+		assert_eq!(ByteLen::of_utf16('😀'), ByteLen(4));
+		assert_eq!(CodeUnitLen::of_utf16('😀'), CodeUnitLen(2));
+	}
+
+	#[test]
+	fn sum() {
+		let s = "aé😀";
+		let bytes: ByteLen = s.decoded_chars().map(|c| c.len()).sum();
+		let units: Utf16Len = s.decoded_chars().map(|c| c.to_utf16_len().len()).sum();
+		let chars: CharLen = s.decoded_chars().map(|c| c.to_char_len().len()).sum();
+		assert_eq!(bytes, ByteLen(s.len()));
+		assert_eq!(units, Utf16Len(s.encode_utf16().count()));
+		assert_eq!(chars, CharLen(3));
+	}
+}
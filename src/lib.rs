@@ -1,109 +1,295 @@
 //! This is a very simple utility crate that provides a wrapper over `char`
-//! values, `DecodedChar`, additionally storing the original byte length of the
+//! values, `DecodedChar`, additionally storing the original length of the
 //! character in the encoded source file.
 //!
+//! The unit of this length is part of the type: `DecodedChar` (or
+//! `DecodedChar<ByteLen>`) stores a length in bytes, while
+//! `DecodedChar<Utf16Len>` stores a length in UTF-16 code units,
+//! `DecodedChar<CharLen>` a length in characters and
+//! `DecodedChar<CodeUnitLen>` a length in code units of the encoding form of
+//! the source file. Lengths of different units cannot be added together
+//! without an explicit conversion.
+//!
 //! It also provides wrappers around `char` iterators to produce `DecodedChar`
-//! iterators from UTF-8/16 encoded sources.
+//! iterators from UTF-8/16/32 encoded sources.
+//!
+//! UTF-8, UTF-16 and UTF-32 (LE or BE) encoded bytes can also be decoded
+//! directly, without a prior validation pass, using `Utf8BytesDecoded`,
+//! `Utf16BytesDecoded`, `Utf32BytesDecoded` or the `DecodedBytes` extension
+//! trait. Byte slices can be decoded from both ends with `Utf8SliceDecoded`,
+//! and UTF-8 encoded readers can be decoded without loading them in memory
+//! with `ReaderDecoded`. Asynchronous readers are supported through the
+//! `futures` feature (`AsyncReaderDecoded`, over `futures::io::AsyncBufRead`)
+//! and the `tokio` feature (`TokioReaderDecoded`, over
+//! `tokio::io::AsyncBufRead`), both providing a `Stream` of decoded
+//! characters. Input arriving in arbitrary chunks can be decoded with the
+//! push-based `Utf8Decoder`.
+//!
+//! When the encoding form of the input is not known in advance, `BomDecoded`
+//! selects it from the byte order mark, which can either be skipped or
+//! emitted as a character.
+//!
+//! Legacy single-byte encodings (ISO-8859-x, Windows-874 and 125x,
+//! KOI8-R/U, Mac Roman and Cyrillic, IBM866) are decoded with
+//! `SingleByteDecoded`, using the index tables of the WHATWG Encoding
+//! Standard. The multi-byte CJK encodings of the standard (Shift_JIS, EUC-JP,
+//! EUC-KR, GBK/GB18030 and Big5) are decoded with `CjkDecoded`, available
+//! with the `cjk` feature, where each character keeps the byte length of its
+//! sequence.
+//! Invalid UTF-8 can be decoded lossily with `LossyUtf8Decoded`, where each
+//! replacement character keeps the byte length of the sequence it replaces.
+//!
+//! When the encoding is only known at run time, for instance from an HTTP
+//! header or a document declaration, the `Encoding` designated by a WHATWG
+//! Encoding Standard label can be found with `Encoding::for_label`, and used
+//! to decode bytes with the `decode` function. The encoding of HTML and XML
+//! documents is detected from their byte order mark or their encoding
+//! declaration by `DocumentDecoded`, following the HTML prescan algorithm and
+//! the autodetection appendix of the XML specification. Otherwise, the
+//! `detect` function guesses the encoding of the input from its first bytes,
+//! along with a confidence value.
+//!
+//! The offset of each character in the encoded source file can be tracked
+//! with the `DecodedIterator::with_offsets` adaptor, available on every
+//! iterator over (possibly fallible) decoded characters. Similarly, the
+//! `DecodedIterator::with_positions` adaptor tracks line and column numbers,
+//! and `DecodedIterator::with_lsp_positions` tracks columns in all the
+//! position encodings of the Language Server Protocol (UTF-8, UTF-16 and
+//! UTF-32) at once.
+//!
+//! Lexers can use `Cursor` to accumulate the `Span` of each token while
+//! consuming decoded characters, and parsers can use `Lookahead` to peek
+//! several characters ahead and rewind to a `Checkpoint` after a failed
+//! speculative parse.
+//!
+//! The body of a JSON string literal can be decoded with `JsonStringDecoded`,
+//! where each escape sequence is decoded into a single character whose length
+//! is the length of the whole sequence in the source file. Similarly, the
+//! content of Rust character, byte, string and byte string literals can be
+//! decoded with `RustLiteralDecoded`, and ECMAScript string literals,
+//! template literals and identifiers with `JsLiteralDecoded`. Python string
+//! and bytes literals, including their prefixes, quotes and implicitly
+//! concatenated parts, are decoded with `PythonLiteralDecoded`, where
+//! `\N{...}` escape sequences require the `unicode-names` feature.
 use std::borrow::Borrow;
 use std::ops::Deref;
 
+mod bom;
+#[cfg(feature = "cjk")]
+mod cjk;
+mod detect;
+mod encoding;
+mod error;
+mod indices;
+mod io;
+mod js;
+mod json;
+mod len;
+mod lookahead;
+mod lsp;
+mod position;
+mod prescan;
+mod python;
+mod rust;
+mod single_byte;
+mod span;
+#[cfg(any(feature = "futures", feature = "tokio"))]
+mod stream;
+mod utf16;
+mod utf32;
+mod utf8;
+
+pub use bom::*;
+#[cfg(feature = "cjk")]
+pub use cjk::*;
+pub use detect::*;
+pub use encoding::*;
+pub use error::*;
+pub use indices::*;
+pub use io::*;
+pub use js::*;
+pub use json::*;
+pub use len::*;
+pub use lookahead::*;
+pub use lsp::*;
+pub use position::*;
+pub use prescan::*;
+pub use python::*;
+pub use rust::*;
+pub use single_byte::*;
+pub use span::*;
+#[cfg(any(feature = "futures", feature = "tokio"))]
+pub use stream::*;
+pub use utf16::*;
+pub use utf32::*;
+pub use utf8::*;
+
 /// Decoded character.
 ///
-/// A character and its original byte length in the encoded source file.
+/// A character and its original length in the encoded source file, expressed
+/// in the unit `L` (bytes by default).
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
-pub struct DecodedChar {
+pub struct DecodedChar<L = ByteLen> {
 	/// Character.
 	c: char,
 
-	/// Byte length in the encoded source file.
-	len: usize,
+	/// Length in the encoded source file.
+	len: L,
 }
 
-impl DecodedChar {
+impl<L> DecodedChar<L> {
 	/// Creates a new decoded character from its value, `c`,
-	/// and its original byte length `len` in the encoded source file.
+	/// and its original length `len` in the encoded source file.
 	#[inline(always)]
-	pub fn new(c: char, len: usize) -> Self {
+	pub fn new(c: char, len: L) -> Self {
 		Self { c, len }
 	}
 
+	/// Returns the character.
+	#[inline(always)]
+	pub fn chr(&self) -> char {
+		self.c
+	}
+
+	/// Returns the original length of the character in the encoded source
+	/// file.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> L
+	where
+		L: Copy,
+	{
+		self.len
+	}
+
+	/// Turns this `DecodedChar` into the underlying `char`.
+	#[inline(always)]
+	pub fn into_char(self) -> char {
+		self.c
+	}
+
+	/// Turns this `DecodedChar` into the original length in the encoded
+	/// source file.
+	#[inline(always)]
+	pub fn into_len(self) -> L {
+		self.len
+	}
+
+	/// Returns the same character with its length expressed in UTF-16 code
+	/// units.
+	///
+	/// This is the number of code units the character occupies once encoded
+	/// in UTF-16, whatever the encoding of the source file.
+	#[inline(always)]
+	pub fn to_utf16_len(&self) -> DecodedChar<Utf16Len> {
+		DecodedChar::new(self.c, Utf16Len(self.c.len_utf16()))
+	}
+
+	/// Returns the same character with its length expressed in characters,
+	/// which is always 1.
+	#[inline(always)]
+	pub fn to_char_len(&self) -> DecodedChar<CharLen> {
+		DecodedChar::new(self.c, CharLen(1))
+	}
+
+	/// Returns the same character with its length expressed in code units of
+	/// the given encoding form.
+	///
+	/// This is the number of code units the character occupies once encoded
+	/// in this form, whatever the encoding of the source file.
+	#[inline(always)]
+	pub fn to_code_unit_len(&self, encoding: UnicodeEncoding) -> DecodedChar<CodeUnitLen> {
+		let len = match encoding {
+			UnicodeEncoding::Utf8 => self.c.len_utf8(),
+			UnicodeEncoding::Utf16Le | UnicodeEncoding::Utf16Be => self.c.len_utf16(),
+			UnicodeEncoding::Utf32Le | UnicodeEncoding::Utf32Be => 1,
+		};
+
+		DecodedChar::new(self.c, CodeUnitLen(len))
+	}
+}
+
+impl DecodedChar {
 	/// Creates a new decoded character,
 	/// decoded from an UTF-8 encoded source file.
 	#[inline(always)]
 	pub fn from_utf8(c: char) -> Self {
 		Self {
 			c,
-			len: c.len_utf8(),
+			len: ByteLen(c.len_utf8()),
 		}
 	}
 
 	/// Creates a new decoded character,
 	/// decoded from an UTF-16 encoded source file.
+	///
+	/// The length is expressed in bytes, two per UTF-16 code unit.
 	#[inline(always)]
 	pub fn from_utf16(c: char) -> Self {
 		Self {
 			c,
-			len: c.len_utf16(),
+			len: ByteLen::of_utf16(c),
 		}
 	}
 
-	/// Returns the character.
-	#[inline(always)]
-	pub fn chr(&self) -> char {
-		self.c
-	}
-
-	/// Returns the original byte length of the character in the encoded source
-	/// file.
+	/// Creates a new decoded character,
+	/// decoded from an UTF-32 encoded source file.
 	#[inline(always)]
-	#[allow(clippy::len_without_is_empty)]
-	pub fn len(&self) -> usize {
-		self.len
+	pub fn from_utf32(c: char) -> Self {
+		Self { c, len: ByteLen(4) }
 	}
+}
 
-	/// Turns this `DecodedChar` into the underlying `char`.
+impl DecodedChar<Utf16Len> {
+	/// Creates a new decoded character,
+	/// decoded from an UTF-16 encoded source file.
+	///
+	/// The length is expressed in UTF-16 code units.
 	#[inline(always)]
-	pub fn into_char(self) -> char {
-		self.c
+	pub fn from_utf16_code_units(c: char) -> Self {
+		Self {
+			c,
+			len: Utf16Len::of_utf16(c),
+		}
 	}
 
-	/// Turns this `DecodedChar` into the original byte length in the encoded
-	/// source file.
+	/// Returns the same character with its length expressed in bytes,
+	/// assuming the source file is UTF-16 encoded.
 	#[inline(always)]
-	pub fn into_len(self) -> usize {
-		self.len
+	pub fn to_byte_len(&self) -> DecodedChar {
+		DecodedChar::new(self.c, self.len.to_byte_len())
 	}
 }
 
-impl From<DecodedChar> for char {
+impl<L> From<DecodedChar<L>> for char {
 	#[inline(always)]
-	fn from(dc: DecodedChar) -> Self {
+	fn from(dc: DecodedChar<L>) -> Self {
 		dc.into_char()
 	}
 }
 
-impl From<DecodedChar> for u32 {
+impl<L> From<DecodedChar<L>> for u32 {
 	#[inline(always)]
-	fn from(dc: DecodedChar) -> Self {
+	fn from(dc: DecodedChar<L>) -> Self {
 		dc.into_char().into()
 	}
 }
 
-impl AsRef<char> for DecodedChar {
+impl<L> AsRef<char> for DecodedChar<L> {
 	#[inline(always)]
 	fn as_ref(&self) -> &char {
 		&self.c
 	}
 }
 
-impl Borrow<char> for DecodedChar {
+impl<L> Borrow<char> for DecodedChar<L> {
 	#[inline(always)]
 	fn borrow(&self) -> &char {
 		&self.c
 	}
 }
 
-impl Deref for DecodedChar {
+impl<L> Deref for DecodedChar<L> {
 	type Target = char;
 
 	#[inline(always)]
@@ -112,99 +298,210 @@ impl Deref for DecodedChar {
 	}
 }
 
-/// Iterator wrapper around UTF-8 encoded sources.
-#[derive(Clone, Debug)]
-pub struct Utf8Decoded<C>(pub C);
+/// Byte order of multi-byte code units.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Endianness {
+	/// Little endian: least significant byte first.
+	Little,
 
-impl<C> Utf8Decoded<C> {
-	#[inline(always)]
-	pub fn new(chars: C) -> Self {
-		Self(chars)
-	}
+	/// Big endian: most significant byte first.
+	Big,
 }
 
-impl<C: Iterator<Item = char>> Iterator for Utf8Decoded<C> {
-	type Item = DecodedChar;
+/// Extension for `str` providing the `decoded_chars` and
+/// `decoded_char_indices` methods.
+pub trait DecodedChars {
+	/// Returns an iterator over the UTF-8 decoded characters of the string,
+	/// wrapped inside a `DecodedChar`.
+	fn decoded_chars(&self) -> Utf8Decoded<std::str::Chars<'_>>;
 
-	#[inline(always)]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.0.next().map(DecodedChar::from_utf8)
-	}
+	/// Returns an iterator over the UTF-8 decoded characters of the string,
+	/// wrapped inside a `DecodedChar`, and their byte offset.
+	///
+	/// The returned iterator is double-ended.
+	fn decoded_char_indices(&self) -> BoundedCharIndices<Utf8Decoded<std::str::Chars<'_>>>;
 }
 
-/// Iterator wrapper around fallible UTF-8 encoded sources.
-#[derive(Clone, Debug)]
-pub struct FallibleUtf8Decoded<C>(pub C);
+impl DecodedChars for str {
+	fn decoded_chars(&self) -> Utf8Decoded<std::str::Chars<'_>> {
+		Utf8Decoded(self.chars())
+	}
 
-impl<C> FallibleUtf8Decoded<C> {
-	#[inline(always)]
-	pub fn new(chars: C) -> Self {
-		Self(chars)
+	fn decoded_char_indices(&self) -> BoundedCharIndices<Utf8Decoded<std::str::Chars<'_>>> {
+		BoundedCharIndices::new(self.decoded_chars(), 0, self.len())
 	}
 }
 
-impl<E, C: Iterator<Item = Result<char, E>>> Iterator for FallibleUtf8Decoded<C> {
-	type Item = Result<DecodedChar, E>;
-
-	#[inline(always)]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.0
-			.next()
-			.map(|result| result.map(DecodedChar::from_utf8))
-	}
+/// Extension for `[u8]` providing the `decoded_utf8_chars`,
+/// `decoded_utf8_chars_lossy`, `decoded_utf16_chars`, `decoded_utf32_chars`
+/// `decoded_chars_with_bom`, `decoded_single_byte_chars`,
+/// `decoded_cjk_chars` (with the `cjk` feature) and
+/// `decoded_chars_with_encoding` methods.
+pub trait DecodedBytes {
+	/// Returns an iterator over the characters decoded from the UTF-8 encoded
+	/// bytes, wrapped inside a `DecodedChar`.
+	///
+	/// Invalid byte sequences are reported as errors.
+	///
+	/// The returned iterator is double-ended, and can decode characters from
+	/// the end of the slice.
+	fn decoded_utf8_chars(&self) -> Utf8SliceDecoded<'_>;
+
+	/// Returns an iterator over the characters decoded from the UTF-8 encoded
+	/// bytes, wrapped inside a `DecodedChar`.
+	///
+	/// Invalid byte sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`,
+	/// whose length is the length of the replaced sequence.
+	fn decoded_utf8_chars_lossy(
+		&self,
+	) -> LossyUtf8Decoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the UTF-16 encoded
+	/// bytes, with the given byte order, wrapped inside a `DecodedChar`.
+	///
+	/// Unpaired surrogates are reported as errors.
+	fn decoded_utf16_chars(
+		&self,
+		endianness: Endianness,
+	) -> Utf16BytesDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the UTF-32 encoded
+	/// bytes, with the given byte order, wrapped inside a `DecodedChar`.
+	///
+	/// Surrogates and out of range code points are reported as errors.
+	fn decoded_utf32_chars(
+		&self,
+		endianness: Endianness,
+	) -> Utf32BytesDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the bytes,
+	/// wrapped inside a `DecodedChar`, using the Unicode encoding form given
+	/// by the byte order mark (UTF-8 if none).
+	fn decoded_chars_with_bom(
+		&self,
+		handling: BomHandling,
+	) -> BomDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the bytes with the
+	/// given single-byte encoding, wrapped inside a `DecodedChar`.
+	///
+	/// Unmapped bytes are reported as errors.
+	fn decoded_single_byte_chars(
+		&self,
+		encoding: SingleByteEncoding,
+	) -> SingleByteDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the bytes with the
+	/// given multi-byte CJK encoding, wrapped inside a `DecodedChar`.
+	///
+	/// Invalid byte sequences are reported as errors.
+	#[cfg(feature = "cjk")]
+	fn decoded_cjk_chars(
+		&self,
+		encoding: CjkEncoding,
+	) -> CjkDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
+
+	/// Returns an iterator over the characters decoded from the bytes with the
+	/// given encoding, wrapped inside a `DecodedChar`.
+	///
+	/// Invalid byte sequences are reported as errors.
+	fn decoded_chars_with_encoding(
+		&self,
+		encoding: Encoding,
+	) -> EncodingDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>>;
 }
 
-/// Iterator wrapper around UTF-16 encoded sources.
-#[derive(Clone, Debug)]
-pub struct Utf16Decoded<C>(pub C);
+impl DecodedBytes for [u8] {
+	fn decoded_utf8_chars(&self) -> Utf8SliceDecoded<'_> {
+		Utf8SliceDecoded::new(self)
+	}
 
-impl<C> Utf16Decoded<C> {
-	#[inline(always)]
-	pub fn new(chars: C) -> Self {
-		Self(chars)
+	fn decoded_utf8_chars_lossy(
+		&self,
+	) -> LossyUtf8Decoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		LossyUtf8Decoded::new(self.iter().copied())
 	}
-}
 
-impl<C: Iterator<Item = char>> Iterator for Utf16Decoded<C> {
-	type Item = DecodedChar;
+	fn decoded_utf16_chars(
+		&self,
+		endianness: Endianness,
+	) -> Utf16BytesDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		Utf16BytesDecoded::new(self.iter().copied(), endianness)
+	}
 
-	#[inline(always)]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.0.next().map(DecodedChar::from_utf16)
+	fn decoded_utf32_chars(
+		&self,
+		endianness: Endianness,
+	) -> Utf32BytesDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		Utf32BytesDecoded::new(self.iter().copied(), endianness)
 	}
-}
 
-/// Iterator wrapper around fallible UTF-16 encoded sources.
-#[derive(Clone, Debug)]
-pub struct FallibleUtf16Decoded<C>(pub C);
+	fn decoded_chars_with_bom(
+		&self,
+		handling: BomHandling,
+	) -> BomDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		BomDecoded::new(self.iter().copied(), handling)
+	}
 
-impl<C> FallibleUtf16Decoded<C> {
-	#[inline(always)]
-	pub fn new(chars: C) -> Self {
-		Self(chars)
+	fn decoded_single_byte_chars(
+		&self,
+		encoding: SingleByteEncoding,
+	) -> SingleByteDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		SingleByteDecoded::new(self.iter().copied(), encoding)
 	}
-}
 
-impl<E, C: Iterator<Item = Result<char, E>>> Iterator for FallibleUtf16Decoded<C> {
-	type Item = Result<DecodedChar, E>;
+	#[cfg(feature = "cjk")]
+	fn decoded_cjk_chars(
+		&self,
+		encoding: CjkEncoding,
+	) -> CjkDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		CjkDecoded::new(self.iter().copied(), encoding)
+	}
 
-	#[inline(always)]
-	fn next(&mut self) -> Option<Self::Item> {
-		self.0
-			.next()
-			.map(|result| result.map(DecodedChar::from_utf16))
+	fn decoded_chars_with_encoding(
+		&self,
+		encoding: Encoding,
+	) -> EncodingDecoded<std::iter::Copied<std::slice::Iter<'_, u8>>> {
+		EncodingDecoded::new(self.iter().copied(), encoding)
 	}
 }
 
-/// Extension for `str` providing the `decoded_char` method.
-pub trait DecodedChars {
-	/// Returns an iterator over the UTF-8 decoded characters of the string,
-	/// wrapped inside a `DecodedChar`.
-	fn decoded_chars(&self) -> Utf8Decoded<std::str::Chars>;
-}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lengths() {
+		let lens = |c| {
+			(
+				DecodedChar::from_utf8(c).len(),
+				DecodedChar::from_utf16(c).len(),
+				DecodedChar::from_utf32(c).len(),
+				DecodedChar::from_utf16_code_units(c).len(),
+			)
+		};
+
+		assert_eq!(lens('a'), (ByteLen(1), ByteLen(2), ByteLen(4), Utf16Len(1)));
+		assert_eq!(lens('é'), (ByteLen(2), ByteLen(2), ByteLen(4), Utf16Len(1)));
+		assert_eq!(
+			lens('😀'),
+			(ByteLen(4), ByteLen(4), ByteLen(4), Utf16Len(2))
+		);
+	}
 
-impl DecodedChars for str {
-	fn decoded_chars(&self) -> Utf8Decoded<std::str::Chars> {
-		Utf8Decoded(self.chars())
+	#[test]
+	fn conversions() {
+		assert_eq!(
+			DecodedChar::from_utf8('é').to_utf16_len().len(),
+			Utf16Len(1)
+		);
+		assert_eq!(
+			DecodedChar::from_utf8('😀').to_utf16_len().len(),
+			Utf16Len(2)
+		);
+		assert_eq!(
+			DecodedChar::from_utf16_code_units('😀').to_byte_len(),
+			DecodedChar::from_utf16('😀')
+		);
 	}
 }
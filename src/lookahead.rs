@@ -0,0 +1,321 @@
+use crate::{DecodedItem, Length};
+use std::collections::VecDeque;
+
+/// Saved state of a [`Lookahead`] stream.
+///
+/// Created by [`Lookahead::checkpoint`], and consumed by either
+/// [`Lookahead::restore`] or [`Lookahead::commit`].
+#[derive(Debug)]
+#[must_use = "checkpoints must be restored or committed"]
+pub struct Checkpoint {
+	/// Index of the next item.
+	index: usize,
+
+	/// Offset of the next item.
+	offset: usize,
+}
+
+impl Checkpoint {
+	/// Returns the offset of the next character at the time of the
+	/// checkpoint.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+
+/// Buffered lookahead over decoded characters, with checkpoint/restore
+/// support.
+///
+/// Items can be peeked at any distance with [`Lookahead::peek_nth`]. The
+/// stream can be rewound to a previous [`Checkpoint`] (for instance after a
+/// failed speculative parse); the offset of the next character is restored
+/// along with it. Consumed items are kept in memory only while a checkpoint
+/// is active.
+///
+/// A bounded stream (see [`Lookahead::bounded`]) never buffers more than a
+/// given number of items, whether they are peeked or kept for a checkpoint.
+/// When the buffer is full, the oldest consumed items are discarded, and the
+/// checkpoints referring to them can no longer be restored.
+///
+/// Checkpoints are expected to be restored or committed in the reverse order
+/// of their creation.
+#[derive(Clone, Debug)]
+pub struct Lookahead<I: Iterator> {
+	/// Decoded characters.
+	iter: I,
+
+	/// Buffered items, starting with the oldest item that can be rewound to.
+	buffer: VecDeque<I::Item>,
+
+	/// Index of the first buffered item.
+	start: usize,
+
+	/// Index of the next item.
+	index: usize,
+
+	/// Offset of the next item.
+	offset: usize,
+
+	/// Number of active checkpoints.
+	checkpoints: usize,
+
+	/// Maximum number of buffered items, if bounded.
+	limit: Option<usize>,
+}
+
+impl<I: Iterator> Lookahead<I>
+where
+	I::Item: DecodedItem + Clone,
+{
+	/// Creates a new unbounded lookahead stream.
+	#[inline(always)]
+	pub fn new(iter: I) -> Self {
+		Self::with_start(iter, 0)
+	}
+
+	/// Creates a new unbounded lookahead stream where the first character is
+	/// at the given offset.
+	#[inline(always)]
+	pub fn with_start(iter: I, offset: usize) -> Self {
+		Self {
+			iter,
+			buffer: VecDeque::new(),
+			start: 0,
+			index: 0,
+			offset,
+			checkpoints: 0,
+			limit: None,
+		}
+	}
+
+	/// Creates a new lookahead stream buffering at most `limit` items.
+	///
+	/// At most `limit` items can be peeked at once, and at most `limit` items
+	/// are kept for the active checkpoints, including the peeked ones.
+	///
+	/// # Panics
+	///
+	/// Panics if `limit` is 0.
+	#[inline(always)]
+	pub fn bounded(iter: I, limit: usize) -> Self {
+		assert!(limit > 0, "lookahead limit must be positive");
+		Self {
+			limit: Some(limit),
+			..Self::new(iter)
+		}
+	}
+
+	/// Returns the maximum number of buffered items, if bounded.
+	#[inline(always)]
+	pub fn limit(&self) -> Option<usize> {
+		self.limit
+	}
+
+	/// Returns the offset of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns a reference to the next item, without consuming it.
+	#[inline(always)]
+	pub fn peek(&mut self) -> Option<&I::Item> {
+		self.peek_nth(0)
+	}
+
+	/// Returns a reference to the `n`-th next item (starting from 0), without
+	/// consuming anything.
+	///
+	/// Returns `None` if the end of the stream is reached before, or if `n`
+	/// exceeds the lookahead limit.
+	pub fn peek_nth(&mut self, n: usize) -> Option<&I::Item> {
+		if self.limit.is_some_and(|limit| n >= limit) {
+			return None;
+		}
+
+		let i = self.fill(n)?;
+		self.buffer.get(i)
+	}
+
+	/// Buffers the `n`-th next item, and returns its index in the buffer.
+	///
+	/// If the stream is bounded and the buffer is full, the oldest consumed
+	/// items are discarded. The caller must ensure that `n` does not exceed
+	/// the limit.
+	fn fill(&mut self, n: usize) -> Option<usize> {
+		loop {
+			let i = self.index - self.start + n;
+			if i < self.buffer.len() {
+				break Some(i);
+			}
+
+			if self.limit.is_some_and(|limit| self.buffer.len() >= limit) {
+				self.buffer.pop_front();
+				self.start += 1
+			} else {
+				self.buffer.push_back(self.iter.next()?)
+			}
+		}
+	}
+
+	/// Consumes and returns the next item.
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Option<I::Item> {
+		let i = self.fill(0)?;
+		let item = if self.checkpoints == 0 {
+			self.start += 1;
+			self.buffer.pop_front().unwrap()
+		} else {
+			self.buffer[i].clone()
+		};
+
+		self.index += 1;
+		self.offset += item.source_len().get();
+		Some(item)
+	}
+
+	/// Saves the current state of the stream.
+	#[inline(always)]
+	pub fn checkpoint(&mut self) -> Checkpoint {
+		self.checkpoints += 1;
+		Checkpoint {
+			index: self.index,
+			offset: self.offset,
+		}
+	}
+
+	/// Rewinds the stream to the given checkpoint.
+	///
+	/// # Panics
+	///
+	/// Panics if the checkpoint does not belong to this stream, if an older
+	/// checkpoint has already been released, or if the items consumed since
+	/// the checkpoint have been discarded by a bounded stream.
+	pub fn restore(&mut self, checkpoint: Checkpoint) {
+		assert!(self.try_restore(checkpoint), "invalid checkpoint")
+	}
+
+	/// Rewinds the stream to the given checkpoint, if the items consumed
+	/// since the checkpoint are still buffered.
+	///
+	/// Otherwise, which can only happen in a bounded stream, the checkpoint
+	/// is released without rewinding the stream, and `false` is returned.
+	pub fn try_restore(&mut self, checkpoint: Checkpoint) -> bool {
+		assert!(self.checkpoints > 0, "invalid checkpoint");
+		let restored = checkpoint.index >= self.start;
+		if restored {
+			self.index = checkpoint.index;
+			self.offset = checkpoint.offset;
+		}
+
+		self.release();
+		restored
+	}
+
+	/// Releases the given checkpoint, without rewinding the stream.
+	pub fn commit(&mut self, _checkpoint: Checkpoint) {
+		assert!(self.checkpoints > 0, "invalid checkpoint");
+		self.release()
+	}
+
+	/// Releases a checkpoint, discarding the consumed items when no
+	/// checkpoint is left.
+	fn release(&mut self) {
+		self.checkpoints -= 1;
+		if self.checkpoints == 0 {
+			self.buffer.drain(..(self.index - self.start));
+			self.start = self.index
+		}
+	}
+}
+
+impl<I: Iterator> Iterator for Lookahead<I>
+where
+	I::Item: DecodedItem + Clone,
+{
+	type Item = I::Item;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		Lookahead::next(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DecodedChars, Utf8BytesDecoded};
+
+	#[test]
+	fn peek() {
+		let mut stream = Lookahead::new("aé😀".decoded_chars());
+		assert_eq!(stream.peek_nth(2).map(|c| c.chr()), Some('😀'));
+		assert_eq!(stream.peek_nth(3), None);
+		assert_eq!(stream.next().map(|c| c.chr()), Some('a'));
+		assert_eq!(stream.peek().map(|c| c.chr()), Some('é'));
+		assert_eq!(stream.offset(), 1);
+	}
+
+	#[test]
+	fn checkpoint_restore() {
+		let mut stream = Lookahead::with_start("abcd".decoded_chars(), 10);
+		stream.next();
+
+		let outer = stream.checkpoint();
+		assert_eq!(outer.offset(), 11);
+		stream.next();
+
+		let inner = stream.checkpoint();
+		stream.next();
+		stream.next();
+		assert_eq!(stream.offset(), 14);
+		stream.restore(inner);
+		assert_eq!(stream.offset(), 12);
+		assert_eq!(stream.peek().map(|c| c.chr()), Some('c'));
+
+		stream.restore(outer);
+		assert_eq!(stream.offset(), 11);
+		let rest: String = stream.map(|c| c.chr()).collect();
+		assert_eq!(rest, "bcd");
+	}
+
+	#[test]
+	fn checkpoint_commit() {
+		let mut stream = Lookahead::new(Utf8BytesDecoded::new(b"a\xffb".iter().copied()));
+		let checkpoint = stream.checkpoint();
+		stream.next();
+		stream.next();
+		stream.commit(checkpoint);
+		assert_eq!(stream.offset(), 2);
+		assert_eq!(stream.next().map(|c| c.unwrap().chr()), Some('b'));
+		assert_eq!(stream.next(), None);
+	}
+
+	#[test]
+	fn bounded() {
+		let mut stream = Lookahead::bounded("abcdef".decoded_chars(), 2);
+		assert_eq!(stream.limit(), Some(2));
+		assert_eq!(stream.peek_nth(1).map(|c| c.chr()), Some('b'));
+		assert_eq!(stream.peek_nth(2), None);
+
+		// Restoring within the bound.
+		let checkpoint = stream.checkpoint();
+		stream.next();
+		stream.next();
+		assert!(stream.try_restore(checkpoint));
+		assert_eq!(stream.offset(), 0);
+
+		// The consumed items are discarded past the bound.
+		let checkpoint = stream.checkpoint();
+		stream.next();
+		stream.next();
+		stream.next();
+		assert!(stream.buffer.len() <= 2);
+		assert!(!stream.try_restore(checkpoint));
+		assert_eq!(stream.offset(), 3);
+
+		let rest: String = stream.map(|c| c.chr()).collect();
+		assert_eq!(rest, "def");
+	}
+}
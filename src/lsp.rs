@@ -0,0 +1,213 @@
+use crate::{Column, DecodedChars};
+use std::fmt;
+use std::str::FromStr;
+
+/// Position encoding, as negotiated between a language server and its client
+/// (`PositionEncodingKind` in the Language Server Protocol).
+///
+/// Defines the unit in which columns are counted.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum PositionEncoding {
+	/// Columns are counted in UTF-8 code units (bytes).
+	Utf8,
+
+	/// Columns are counted in UTF-16 code units.
+	///
+	/// This is the default encoding of the Language Server Protocol.
+	Utf16,
+
+	/// Columns are counted in UTF-32 code units (characters).
+	Utf32,
+}
+
+impl Default for PositionEncoding {
+	#[inline(always)]
+	fn default() -> Self {
+		Self::Utf16
+	}
+}
+
+impl PositionEncoding {
+	/// Returns the name of this encoding in the Language Server Protocol.
+	#[inline(always)]
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Utf8 => "utf-8",
+			Self::Utf16 => "utf-16",
+			Self::Utf32 => "utf-32",
+		}
+	}
+
+	/// Returns the number of code units of the given character in this
+	/// encoding.
+	#[inline(always)]
+	pub fn len_of(&self, c: char) -> usize {
+		match self {
+			Self::Utf8 => c.len_utf8(),
+			Self::Utf16 => c.len_utf16(),
+			Self::Utf32 => 1,
+		}
+	}
+
+	/// Converts a column of the given line from this encoding to the `target`
+	/// encoding.
+	///
+	/// Following the Language Server Protocol, a column past the end of the
+	/// line is clamped to the end of the line. A column pointing inside a
+	/// character is moved back to the start of the character.
+	#[inline(always)]
+	pub fn convert_column(&self, line: &str, column: usize, target: Self) -> usize {
+		Columns::in_line(line, column, *self).get(target)
+	}
+}
+
+impl fmt::Display for PositionEncoding {
+	#[inline(always)]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.as_str().fmt(f)
+	}
+}
+
+/// Unknown position encoding name.
+#[derive(Clone, Debug)]
+pub struct UnknownPositionEncoding(pub String);
+
+impl fmt::Display for UnknownPositionEncoding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "unknown position encoding `{}`", self.0)
+	}
+}
+
+impl std::error::Error for UnknownPositionEncoding {}
+
+impl FromStr for PositionEncoding {
+	type Err = UnknownPositionEncoding;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"utf-8" => Ok(Self::Utf8),
+			"utf-16" => Ok(Self::Utf16),
+			"utf-32" => Ok(Self::Utf32),
+			_ => Err(UnknownPositionEncoding(s.to_owned())),
+		}
+	}
+}
+
+/// Column expressed simultaneously in all the LSP position encodings.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Columns {
+	/// Column in UTF-8 code units (bytes).
+	pub utf8: usize,
+
+	/// Column in UTF-16 code units.
+	pub utf16: usize,
+
+	/// Column in UTF-32 code units (characters).
+	pub utf32: usize,
+}
+
+impl Columns {
+	/// Computes the columns of the given line at `column`, expressed in the
+	/// given encoding.
+	///
+	/// A column past the end of the line is clamped to the end of the line.
+	/// A column pointing inside a character is moved back to the start of the
+	/// character.
+	pub fn in_line(line: &str, column: usize, encoding: PositionEncoding) -> Self {
+		let mut result = Self::default();
+
+		for c in line.decoded_chars() {
+			if matches!(*c, '\n' | '\r') {
+				break;
+			}
+
+			let mut next = result;
+			next.advance(*c);
+
+			if next.get(encoding) > column {
+				break;
+			}
+
+			result = next
+		}
+
+		result
+	}
+
+	/// Returns the column in the given encoding.
+	#[inline(always)]
+	pub fn get(&self, encoding: PositionEncoding) -> usize {
+		match encoding {
+			PositionEncoding::Utf8 => self.utf8,
+			PositionEncoding::Utf16 => self.utf16,
+			PositionEncoding::Utf32 => self.utf32,
+		}
+	}
+}
+
+impl Column for Columns {
+	#[inline(always)]
+	fn advance(&mut self, c: char) {
+		self.utf8 += c.len_utf8();
+		self.utf16 += c.len_utf16();
+		self.utf32 += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedIterator;
+
+	#[test]
+	fn lsp_positions() {
+		let columns: Vec<_> = "a😀é\nb"
+			.decoded_chars()
+			.with_lsp_positions()
+			.map(|(p, _)| (p.line, p.column.utf8, p.column.utf16, p.column.utf32))
+			.collect();
+		assert_eq!(
+			columns,
+			[
+				(0, 0, 0, 0),
+				(0, 1, 1, 1),
+				(0, 5, 3, 2),
+				(0, 7, 4, 3),
+				(1, 0, 0, 0)
+			]
+		);
+	}
+
+	#[test]
+	fn convert_column() {
+		use PositionEncoding::*;
+		let line = "a😀é\n";
+		assert_eq!(Utf16.convert_column(line, 3, Utf8), 5);
+		assert_eq!(Utf8.convert_column(line, 5, Utf16), 3);
+		assert_eq!(Utf32.convert_column(line, 2, Utf16), 3);
+
+		// Inside a character.
+		assert_eq!(Utf16.convert_column(line, 2, Utf8), 1);
+		assert_eq!(Utf8.convert_column(line, 6, Utf32), 2);
+
+		// Past the end of the line.
+		assert_eq!(Utf8.convert_column(line, 100, Utf16), 4);
+	}
+
+	#[test]
+	fn names() {
+		for encoding in [
+			PositionEncoding::Utf8,
+			PositionEncoding::Utf16,
+			PositionEncoding::Utf32,
+		] {
+			assert_eq!(
+				encoding.as_str().parse::<PositionEncoding>().unwrap(),
+				encoding
+			);
+		}
+
+		assert!("utf-7".parse::<PositionEncoding>().is_err());
+		assert_eq!(PositionEncoding::default(), PositionEncoding::Utf16);
+	}
+}
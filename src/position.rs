@@ -0,0 +1,270 @@
+use crate::{DecodedItem, Length};
+use std::fmt;
+
+/// Column number.
+///
+/// Implemented by `usize`, counting columns in characters, and by
+/// [`Columns`](crate::Columns), counting columns in all the LSP position
+/// encodings at once.
+pub trait Column: Copy + Default {
+	/// Advances the column past the given character.
+	fn advance(&mut self, c: char);
+}
+
+impl Column for usize {
+	#[inline(always)]
+	fn advance(&mut self, _c: char) {
+		*self += 1
+	}
+}
+
+/// Position in the encoded source file.
+///
+/// Lines and columns start at 0. Columns are counted in characters by
+/// default.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Position<C = usize> {
+	/// Offset in the encoded source file, in the length unit of the decoded
+	/// characters (bytes by default).
+	pub byte: usize,
+
+	/// Line number.
+	pub line: usize,
+
+	/// Column number.
+	pub column: C,
+}
+
+impl fmt::Display for Position {
+	/// Displays the position as `line:column`, both starting at 1, as
+	/// commonly done in diagnostics.
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", self.line + 1, self.column + 1)
+	}
+}
+
+/// Line and column tracker.
+///
+/// Computes the position of each decoded character it is fed with. `\n`,
+/// `\r\n` and `\r` are always treated as line breaks. The Unicode line
+/// breaks `U+0085 NEXT LINE`, `U+2028 LINE SEPARATOR` and
+/// `U+2029 PARAGRAPH SEPARATOR` can be enabled with
+/// [`PositionTracker::with_unicode_line_breaks`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct PositionTracker<C = usize> {
+	/// Position of the next character.
+	position: Position<C>,
+
+	/// Whether or not Unicode line breaks are enabled.
+	unicode_line_breaks: bool,
+
+	/// Position of the last character, if it was a `\r`.
+	cr: Option<Position<C>>,
+}
+
+impl PositionTracker {
+	/// Creates a new tracker starting at the beginning of the source file.
+	#[inline(always)]
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl<C: Column> PositionTracker<C> {
+	/// Creates a new tracker starting at the given position.
+	#[inline(always)]
+	pub fn starting_at(position: Position<C>) -> Self {
+		Self {
+			position,
+			..Self::default()
+		}
+	}
+
+	/// Enables or disables the Unicode line breaks `U+0085`, `U+2028` and
+	/// `U+2029`.
+	#[inline(always)]
+	pub fn with_unicode_line_breaks(mut self, value: bool) -> Self {
+		self.unicode_line_breaks = value;
+		self
+	}
+
+	/// Returns the position of the next character.
+	#[inline(always)]
+	pub fn position(&self) -> Position<C> {
+		self.position
+	}
+
+	/// Checks if the given character is a line break.
+	#[inline(always)]
+	fn is_line_break(&self, c: char) -> bool {
+		match c {
+			'\n' | '\r' => true,
+			'\u{85}' | '\u{2028}' | '\u{2029}' => self.unicode_line_breaks,
+			_ => false,
+		}
+	}
+
+	/// Advances the tracker past a character `c` of the given length.
+	///
+	/// Returns the position of the character. The `\n` of a `\r\n` line
+	/// break is located on the same line as the `\r`.
+	pub fn advance<L: Length>(&mut self, c: char, len: L) -> Position<C> {
+		let cr = self.cr.take();
+		let position = match cr {
+			Some(cr) if c == '\n' => {
+				let mut column = cr.column;
+				column.advance('\r');
+				Position {
+					byte: self.position.byte,
+					line: cr.line,
+					column,
+				}
+			}
+			_ => self.position,
+		};
+
+		self.position.byte += len.get();
+
+		if cr.is_none() || c != '\n' {
+			if self.is_line_break(c) {
+				self.position.line += 1;
+				self.position.column = C::default();
+			} else {
+				self.position.column.advance(c);
+			}
+		}
+
+		if c == '\r' {
+			self.cr = Some(position)
+		}
+
+		position
+	}
+
+	/// Advances the tracker past the given item.
+	///
+	/// Errors (invalid sequences) occupy a single column, like a replacement
+	/// character would.
+	///
+	/// Returns the position of the item.
+	pub fn push<T: DecodedItem>(&mut self, item: &T) -> Position<C> {
+		self.advance(
+			item.decoded_char().unwrap_or(char::REPLACEMENT_CHARACTER),
+			item.source_len(),
+		)
+	}
+}
+
+/// Iterator adaptor yielding decoded characters along with their position in
+/// the encoded source file.
+///
+/// Errors are passed through, and their length (given by
+/// [`SourceLen`](crate::SourceLen)) is taken into account to compute the
+/// position of the following characters.
+#[derive(Clone, Debug)]
+pub struct Positions<I, C = usize> {
+	/// Decoded characters.
+	iter: I,
+
+	/// Position tracker.
+	tracker: PositionTracker<C>,
+}
+
+impl<I, C: Column> Positions<I, C> {
+	#[inline(always)]
+	pub fn new(iter: I, tracker: PositionTracker<C>) -> Self {
+		Self { iter, tracker }
+	}
+
+	/// Returns the position of the next character.
+	#[inline(always)]
+	pub fn position(&self) -> Position<C> {
+		self.tracker.position()
+	}
+
+	/// Returns the underlying iterator.
+	#[inline(always)]
+	pub fn into_inner(self) -> I {
+		self.iter
+	}
+}
+
+impl<I: Iterator, C: Column> Iterator for Positions<I, C>
+where
+	I::Item: DecodedItem,
+{
+	type Item = <I::Item as DecodedItem>::With<Position<C>>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(|item| {
+			let position = self.tracker.push(&item);
+			item.with(position)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DecodedChars, DecodedIterator};
+
+	/// Returns the offset, line and column of each character of the given
+	/// string.
+	fn positions(s: &str) -> Vec<(usize, usize, usize)> {
+		s.decoded_chars()
+			.with_positions()
+			.map(|(p, _)| (p.byte, p.line, p.column))
+			.collect()
+	}
+
+	#[test]
+	fn line_breaks() {
+		assert_eq!(
+			positions("a\r\nb\rc\nd"),
+			[
+				(0, 0, 0),
+				(1, 0, 1),
+				(2, 0, 2),
+				(3, 1, 0),
+				(4, 1, 1),
+				(5, 2, 0),
+				(6, 2, 1),
+				(7, 3, 0)
+			]
+		);
+	}
+
+	#[test]
+	fn crlf_split_by_other_chars() {
+		assert_eq!(
+			positions("\r\r\n\n"),
+			[(0, 0, 0), (1, 1, 0), (2, 1, 1), (3, 2, 0)]
+		);
+	}
+
+	#[test]
+	fn columns_in_characters() {
+		assert_eq!(positions("é😀x"), [(0, 0, 0), (2, 0, 1), (6, 0, 2)]);
+	}
+
+	#[test]
+	fn unicode_line_breaks() {
+		let s = "a\u{2028}b";
+		assert_eq!(positions(s), [(0, 0, 0), (1, 0, 1), (4, 0, 2)]);
+
+		let mut tracker = PositionTracker::new().with_unicode_line_breaks(true);
+		let lines: Vec<_> = s.decoded_chars().map(|c| tracker.push(&c).line).collect();
+		assert_eq!(lines, [0, 0, 1]);
+	}
+
+	#[test]
+	fn display() {
+		let position = Position {
+			byte: 10,
+			line: 2,
+			column: 4,
+		};
+		assert_eq!(position.to_string(), "3:5");
+	}
+}
@@ -0,0 +1,595 @@
+use crate::{
+	DecodeError, DecodedChar, DecodedCharIndices, Encoding, EncodingDecoded, Position,
+	PositionTracker, Positions, SingleByteEncoding, UnicodeEncoding,
+};
+
+/// Number of bytes inspected when looking for an encoding declaration.
+const PRESCAN_LEN: usize = 1024;
+
+/// Checks if the given byte is an ASCII whitespace, as defined by the HTML
+/// standard.
+#[inline(always)]
+fn is_space(b: u8) -> bool {
+	matches!(b, 0x09 | 0x0a | 0x0c | 0x0d | 0x20)
+}
+
+/// Finds the encoding designated by the given label bytes.
+#[inline(always)]
+fn label_encoding(label: &[u8]) -> Option<Encoding> {
+	std::str::from_utf8(label)
+		.ok()
+		.and_then(Encoding::for_label)
+}
+
+/// Checks if the given encoding is ASCII-compatible, so that it can be
+/// declared inside the document it encodes.
+#[inline(always)]
+fn is_ascii_compatible(encoding: Encoding) -> bool {
+	!matches!(
+		encoding,
+		Encoding::Unicode(
+			UnicodeEncoding::Utf16Le
+				| UnicodeEncoding::Utf16Be
+				| UnicodeEncoding::Utf32Le
+				| UnicodeEncoding::Utf32Be
+		)
+	)
+}
+
+/// State of the HTML prescan.
+///
+/// Methods return `None` when the end of the inspected bytes is reached,
+/// which ends the prescan.
+struct HtmlPrescan<'a> {
+	/// Inspected bytes.
+	bytes: &'a [u8],
+
+	/// Position of the current byte.
+	pos: usize,
+}
+
+impl HtmlPrescan<'_> {
+	/// Returns the current byte.
+	#[inline(always)]
+	fn current(&self) -> Option<u8> {
+		self.bytes.get(self.pos).copied()
+	}
+
+	/// Moves to the end of the first occurrence of `pattern` starting at
+	/// `from`.
+	fn skip_past(&mut self, from: usize, pattern: &[u8]) -> Option<()> {
+		let i = self
+			.bytes
+			.get(from..)?
+			.windows(pattern.len())
+			.position(|w| w == pattern)?;
+		self.pos = from + i + pattern.len() - 1;
+		Some(())
+	}
+
+	/// Skips the bytes matching `f`.
+	fn skip_while(&mut self, f: impl Fn(u8) -> bool) -> Option<u8> {
+		loop {
+			let b = self.current()?;
+			if f(b) {
+				self.pos += 1
+			} else {
+				break Some(b);
+			}
+		}
+	}
+
+	/// Runs the prescan.
+	fn run(&mut self) -> Option<Encoding> {
+		loop {
+			let rest = &self.bytes[self.pos..];
+			if rest.starts_with(b"<!--") {
+				self.skip_past(self.pos + 2, b"-->")?
+			} else if rest.len() > 5
+				&& rest[..5].eq_ignore_ascii_case(b"<meta")
+				&& (is_space(rest[5]) || rest[5] == b'/')
+			{
+				self.pos += 6;
+				if let Some(encoding) = self.meta()? {
+					break Some(encoding);
+				}
+			} else if rest.starts_with(b"<")
+				&& (rest.get(1).is_some_and(u8::is_ascii_alphabetic)
+					|| (rest.get(1) == Some(&b'/')
+						&& rest.get(2).is_some_and(u8::is_ascii_alphabetic)))
+			{
+				self.skip_while(|b| !is_space(b) && b != b'>')?;
+				while self.attribute()?.is_some() {}
+			} else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?")
+			{
+				self.skip_past(self.pos + 2, b">")?
+			}
+
+			self.pos += 1;
+			if self.pos >= self.bytes.len() {
+				break None;
+			}
+		}
+	}
+
+	/// Processes the attributes of a `meta` element.
+	///
+	/// Returns the encoding it declares, if any.
+	fn meta(&mut self) -> Option<Option<Encoding>> {
+		let mut names = Vec::new();
+		let mut got_pragma = false;
+		let mut need_pragma = None;
+		let mut charset: Option<Option<Encoding>> = None;
+
+		while let Some((name, value)) = self.attribute()? {
+			if names.contains(&name) {
+				continue;
+			}
+
+			match name.as_slice() {
+				b"http-equiv" => got_pragma |= value == b"content-type",
+				b"content" if charset.is_none() => {
+					if let Some(encoding) = meta_content_encoding(&value) {
+						charset = Some(Some(encoding));
+						need_pragma = Some(true)
+					}
+				}
+				b"charset" => {
+					charset = Some(label_encoding(&value));
+					need_pragma = Some(false)
+				}
+				_ => (),
+			}
+
+			names.push(name);
+		}
+
+		let encoding = match (need_pragma, charset) {
+			(Some(true), _) if !got_pragma => None,
+			(Some(_), Some(Some(encoding))) => Some(encoding),
+			_ => None,
+		};
+
+		Some(encoding.map(|encoding| match encoding {
+			encoding if !is_ascii_compatible(encoding) => Encoding::Unicode(UnicodeEncoding::Utf8),
+			Encoding::SingleByte(SingleByteEncoding::XUserDefined) => {
+				Encoding::SingleByte(SingleByteEncoding::Windows1252)
+			}
+			encoding => encoding,
+		}))
+	}
+
+	/// Reads the next attribute of the current element.
+	///
+	/// Returns its lowercased name and value, or `None` if the end of the
+	/// element is reached.
+	#[allow(clippy::type_complexity)]
+	fn attribute(&mut self) -> Option<Option<(Vec<u8>, Vec<u8>)>> {
+		if self.skip_while(|b| is_space(b) || b == b'/')? == b'>' {
+			return Some(None);
+		}
+
+		let mut name = Vec::new();
+		let mut value = Vec::new();
+
+		loop {
+			match self.current()? {
+				b'=' if !name.is_empty() => {
+					self.pos += 1;
+					break;
+				}
+				b if is_space(b) => {
+					if self.skip_while(is_space)? != b'=' {
+						return Some(Some((name, value)));
+					}
+
+					self.pos += 1;
+					break;
+				}
+				b'/' | b'>' => return Some(Some((name, value))),
+				b => name.push(b.to_ascii_lowercase()),
+			}
+
+			self.pos += 1
+		}
+
+		match self.skip_while(is_space)? {
+			quote @ (b'"' | b'\'') => loop {
+				self.pos += 1;
+				match self.current()? {
+					b if b == quote => {
+						self.pos += 1;
+						break Some(Some((name, value)));
+					}
+					b => value.push(b.to_ascii_lowercase()),
+				}
+			},
+			b'>' => Some(Some((name, value))),
+			_ => loop {
+				match self.current()? {
+					b if is_space(b) || b == b'>' => break Some(Some((name, value))),
+					b => value.push(b.to_ascii_lowercase()),
+				}
+
+				self.pos += 1
+			},
+		}
+	}
+}
+
+/// Extracts the encoding from the (lowercased) `content` attribute of a
+/// `meta` element, such as `text/html; charset=utf-8`.
+fn meta_content_encoding(content: &[u8]) -> Option<Encoding> {
+	let mut pos = 0;
+	loop {
+		pos += content[pos..].windows(7).position(|w| w == b"charset")? + 7;
+		while content.get(pos).copied().is_some_and(is_space) {
+			pos += 1
+		}
+
+		if content.get(pos) == Some(&b'=') {
+			pos += 1;
+			break;
+		}
+	}
+
+	while content.get(pos).copied().is_some_and(is_space) {
+		pos += 1
+	}
+
+	match content.get(pos)? {
+		quote @ (b'"' | b'\'') => {
+			let value = &content[(pos + 1)..];
+			let end = value.iter().position(|b| b == quote)?;
+			label_encoding(&value[..end])
+		}
+		_ => {
+			let value = &content[pos..];
+			let end = value
+				.iter()
+				.position(|&b| is_space(b) || b == b';')
+				.unwrap_or(value.len());
+			label_encoding(&value[..end])
+		}
+	}
+}
+
+/// Finds the encoding declared by a `meta` element in the first 1024 bytes of
+/// an HTML document.
+///
+/// This is the prescan algorithm of the HTML standard. UTF-16 declarations
+/// are interpreted as UTF-8, and `x-user-defined` as Windows-1252.
+pub fn prescan_html(bytes: &[u8]) -> Option<Encoding> {
+	let bytes = &bytes[..bytes.len().min(PRESCAN_LEN)];
+	if bytes.is_empty() {
+		return None;
+	}
+
+	HtmlPrescan { bytes, pos: 0 }.run()
+}
+
+/// Finds the encoding of an XML document, without byte order mark, from its
+/// first bytes and its encoding declaration.
+///
+/// This follows the autodetection appendix of the XML specification: the
+/// UTF-16 and UTF-32 encodings are recognized from the encoded `<?` (or `<`
+/// for UTF-32) starting the document, otherwise the encoding is read from
+/// the `encoding` pseudo-attribute of the XML declaration. A declared encoding
+/// that is not ASCII-compatible is interpreted as UTF-8.
+pub fn prescan_xml(bytes: &[u8]) -> Option<Encoding> {
+	let encoding = match bytes.get(..4)? {
+		[0x00, 0x00, 0x00, 0x3c] => UnicodeEncoding::Utf32Be,
+		[0x3c, 0x00, 0x00, 0x00] => UnicodeEncoding::Utf32Le,
+		[0x00, 0x3c, 0x00, 0x3f] => UnicodeEncoding::Utf16Be,
+		[0x3c, 0x00, 0x3f, 0x00] => UnicodeEncoding::Utf16Le,
+		_ => return xml_declaration_encoding(&bytes[..bytes.len().min(PRESCAN_LEN)]),
+	};
+
+	Some(Encoding::Unicode(encoding))
+}
+
+/// Reads the `encoding` pseudo-attribute of the ASCII-compatible XML
+/// declaration starting the given bytes.
+fn xml_declaration_encoding(bytes: &[u8]) -> Option<Encoding> {
+	let is_space = |b: u8| matches!(b, 0x09 | 0x0a | 0x0d | 0x20);
+	let rest = bytes.strip_prefix(b"<?xml")?;
+	if !is_space(*rest.first()?) {
+		return None;
+	}
+
+	let end = rest.windows(2).position(|w| w == b"?>")?;
+	let mut declaration = &rest[..end];
+	loop {
+		declaration = declaration.trim_ascii_start();
+		let name_len = declaration.iter().position(|&b| is_space(b) || b == b'=')?;
+		let (name, rest) = declaration.split_at(name_len);
+		let rest = rest
+			.trim_ascii_start()
+			.strip_prefix(b"=")?
+			.trim_ascii_start();
+		let quote = *rest.first().filter(|&&b| b == b'"' || b == b'\'')?;
+		let value_len = rest[1..].iter().position(|&b| b == quote)?;
+		if name == b"encoding" {
+			break label_encoding(&rest[1..(1 + value_len)]).map(|encoding| {
+				if is_ascii_compatible(encoding) {
+					encoding
+				} else {
+					Encoding::Unicode(UnicodeEncoding::Utf8)
+				}
+			});
+		}
+
+		declaration = &rest[(value_len + 2)..];
+	}
+}
+
+/// How the encoding of a document was determined.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum EncodingSource {
+	/// Byte order mark.
+	Bom,
+
+	/// Encoding declaration (or, for XML, the encoded form of its first
+	/// characters).
+	Declaration,
+
+	/// Neither a byte order mark nor a declaration was found, and the default
+	/// encoding is used.
+	Default,
+}
+
+/// Decoder for HTML and XML documents, using the encoding given by their
+/// byte order mark or their encoding declaration.
+///
+/// The byte order mark, if any, is skipped, but offsets of the decoded
+/// characters (and errors) always match positions in the original input.
+#[derive(Clone, Debug)]
+pub struct DocumentDecoded<'a> {
+	/// Detected encoding.
+	encoding: Encoding,
+
+	/// How the encoding was detected.
+	source: EncodingSource,
+
+	/// Decoder.
+	inner: EncodingDecoded<std::iter::Copied<std::slice::Iter<'a, u8>>>,
+}
+
+impl<'a> DocumentDecoded<'a> {
+	/// Creates a decoder for the given HTML document.
+	///
+	/// The encoding is given by the UTF-8 or UTF-16 byte order mark, then
+	/// by [`prescan_html`], and is `fallback` if both fail.
+	pub fn html(bytes: &'a [u8], fallback: Encoding) -> Self {
+		let bom = match bytes {
+			[0xef, 0xbb, 0xbf, ..] => Some(UnicodeEncoding::Utf8),
+			[0xfe, 0xff, ..] => Some(UnicodeEncoding::Utf16Be),
+			[0xff, 0xfe, ..] => Some(UnicodeEncoding::Utf16Le),
+			_ => None,
+		};
+
+		match bom {
+			Some(encoding) => Self::new(bytes, encoding.into(), EncodingSource::Bom),
+			None => match prescan_html(bytes) {
+				Some(encoding) => Self::new(bytes, encoding, EncodingSource::Declaration),
+				None => Self::new(bytes, fallback, EncodingSource::Default),
+			},
+		}
+	}
+
+	/// Creates a decoder for the given XML document.
+	///
+	/// The encoding is given by the byte order mark, then by
+	/// [`prescan_xml`], and is UTF-8 if both fail.
+	pub fn xml(bytes: &'a [u8]) -> Self {
+		match UnicodeEncoding::sniff_bom(bytes) {
+			Some(encoding) => Self::new(bytes, encoding.into(), EncodingSource::Bom),
+			None => match prescan_xml(bytes) {
+				Some(encoding) => Self::new(bytes, encoding, EncodingSource::Declaration),
+				None => Self::new(bytes, UnicodeEncoding::Utf8.into(), EncodingSource::Default),
+			},
+		}
+	}
+
+	fn new(bytes: &'a [u8], encoding: Encoding, source: EncodingSource) -> Self {
+		let start = match (source, encoding) {
+			(EncodingSource::Bom, Encoding::Unicode(e)) => e.bom().len(),
+			_ => 0,
+		};
+
+		Self {
+			encoding,
+			source,
+			inner: EncodingDecoded::with_start(bytes[start..].iter().copied(), encoding, start),
+		}
+	}
+
+	/// Returns the detected encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> Encoding {
+		self.encoding
+	}
+
+	/// Returns how the encoding was detected.
+	#[inline(always)]
+	pub fn source(&self) -> EncodingSource {
+		self.source
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.inner.offset()
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// byte offset in the encoded source file, starting after a skipped byte
+	/// order mark.
+	///
+	/// This shadows `DecodedIterator::with_offsets`, which would start at
+	/// offset 0 after a skipped byte order mark while error offsets do not.
+	#[inline(always)]
+	pub fn with_offsets(self) -> DecodedCharIndices<Self> {
+		let offset = self.offset();
+		DecodedCharIndices::with_start(self, offset)
+	}
+
+	/// Returns an iterator yielding each decoded character along with its
+	/// position in the encoded source file, starting after a skipped byte
+	/// order mark.
+	///
+	/// This shadows `DecodedIterator::with_positions`, which would start at
+	/// offset 0 after a skipped byte order mark while error offsets do not.
+	#[inline(always)]
+	pub fn with_positions(self) -> Positions<Self> {
+		let tracker = PositionTracker::starting_at(Position {
+			byte: self.offset(),
+			..Position::default()
+		});
+		Positions::new(self, tracker)
+	}
+}
+
+impl Iterator for DocumentDecoded<'_> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const UTF_8: Encoding = Encoding::Unicode(UnicodeEncoding::Utf8);
+
+	#[test]
+	fn meta() {
+		use SingleByteEncoding::*;
+		let cases: [(&[u8], _); 11] = [
+			(b"<meta charset=\"utf-8\">", Some(UTF_8)),
+			(b"<html><META CHARSET='Latin1'>", Some(Windows1252.into())),
+			(b"<meta charset=koi8-r />", Some(Koi8R.into())),
+			(
+				b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=iso-8859-2\">",
+				Some(Iso8859_2.into()),
+			),
+			(
+				b"<meta content='text/html; charset=\"windows-1251\"' http-equiv=content-type>",
+				Some(Windows1251.into()),
+			),
+			(b"<meta content=\"text/html; charset=iso-8859-2\">", None),
+			(b"<meta charset=utf-16le>", Some(UTF_8)),
+			(b"<meta charset=x-user-defined>", Some(Windows1252.into())),
+			(
+				b"<meta charset=bogus><meta charset=iso-8859-5>",
+				Some(Iso8859_5.into()),
+			),
+			(
+				b"<!-- <meta charset=iso-8859-2> --><meta charset=iso-8859-7>",
+				Some(Iso8859_7.into()),
+			),
+			(b"<p charset=iso-8859-2>", None),
+		];
+
+		for (bytes, expected) in cases {
+			assert_eq!(
+				prescan_html(bytes),
+				expected,
+				"{}",
+				String::from_utf8_lossy(bytes)
+			);
+		}
+	}
+
+	#[test]
+	fn meta_out_of_range() {
+		let mut bytes = vec![b' '; PRESCAN_LEN];
+		bytes.extend_from_slice(b"<meta charset=iso-8859-2>");
+		assert_eq!(prescan_html(&bytes), None);
+		assert_eq!(prescan_html(b""), None);
+	}
+
+	#[test]
+	fn xml() {
+		let cases: [(&[u8], _); 6] = [
+			(
+				b"<?xml version=\"1.0\" encoding=\"ISO-8859-5\"?>",
+				Some(SingleByteEncoding::Iso8859_5.into()),
+			),
+			(b"<?xml version='1.0' encoding = 'utf-16'?>", Some(UTF_8)),
+			(
+				b"<\x00?\x00x\x00m\x00l\x00",
+				Some(UnicodeEncoding::Utf16Le.into()),
+			),
+			(
+				b"\x00\x00\x00<\x00\x00\x00?",
+				Some(UnicodeEncoding::Utf32Be.into()),
+			),
+			(b"<?xml version=\"1.0\"?>", None),
+			(b"<root/>", None),
+		];
+
+		for (bytes, expected) in cases {
+			assert_eq!(
+				prescan_xml(bytes),
+				expected,
+				"{}",
+				String::from_utf8_lossy(bytes)
+			);
+		}
+	}
+
+	#[test]
+	fn document() {
+		let fallback = SingleByteEncoding::Windows1252.into();
+
+		let decoder = DocumentDecoded::html(b"\xef\xbb\xbfa\nb", fallback);
+		assert_eq!(
+			(decoder.encoding(), decoder.source()),
+			(UTF_8, EncodingSource::Bom)
+		);
+		let positions: Vec<_> = decoder
+			.with_positions()
+			.map(|item| {
+				let (p, c) = item.unwrap();
+				(p.byte, p.line, c.chr())
+			})
+			.collect();
+		assert_eq!(positions, [(3, 0, 'a'), (4, 0, '\n'), (5, 1, 'b')]);
+
+		let decoder = DocumentDecoded::html(b"<meta charset=koi8-r>\xc1", fallback);
+		assert_eq!(decoder.source(), EncodingSource::Declaration);
+		let (offset, c) = decoder.with_offsets().last().unwrap().unwrap();
+		assert_eq!((offset, c.chr()), (21, '\u{430}'));
+
+		let decoder = DocumentDecoded::html(b"\xe9", fallback);
+		assert_eq!(
+			(decoder.encoding(), decoder.source()),
+			(fallback, EncodingSource::Default)
+		);
+
+		let decoder = DocumentDecoded::xml(b"\xff\xfe\x00\x00<\x00\x00\x00");
+		assert_eq!(decoder.encoding(), UnicodeEncoding::Utf32Le.into());
+		let offsets: Vec<_> = decoder.with_offsets().map(|item| item.unwrap().0).collect();
+		assert_eq!(offsets, [4]);
+
+		let offsets: Vec<_> = DocumentDecoded::html(b"\xef\xbb\xbfa\xffb", fallback)
+			.with_offsets()
+			.map(|item| {
+				item.map(|(offset, c)| (offset, c.chr()))
+					.map_err(|e| e.offset())
+			})
+			.collect();
+		assert_eq!(offsets, [Ok((3, 'a')), Err(4), Ok((5, 'b'))]);
+
+		let decoder = DocumentDecoded::xml(b"<a/>");
+		assert_eq!(
+			(decoder.encoding(), decoder.source()),
+			(UTF_8, EncodingSource::Default)
+		);
+	}
+}
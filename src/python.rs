@@ -0,0 +1,783 @@
+use crate::{ByteLen, DecodedChar, Length, Lookahead, SourceLen};
+use std::fmt;
+
+/// Kind of Python literal escape error.
+///
+/// These are the errors reported by CPython when tokenizing string and bytes
+/// literals.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PythonEscapeErrorKind {
+	/// Prefix other than `r`, `u`, `b`, `br` or `rb` (in any case), such as
+	/// the prefix of a formatted string literal.
+	UnsupportedPrefix,
+
+	/// Characters that do not start a string or bytes literal.
+	ExpectedLiteral,
+
+	/// Bytes literal concatenated with a string literal, or conversely.
+	MixedBytes,
+
+	/// The input ended before the closing quote of the literal.
+	Unterminated,
+
+	/// Line break in a single-quoted literal.
+	UnescapedNewline,
+
+	/// Non-ASCII character in a bytes literal.
+	NonAsciiCharInBytes(char),
+
+	/// `\x` escape sequence with less than two hexadecimal digits.
+	TruncatedHexEscape,
+
+	/// `\u` or `\U` escape sequence with less than four or eight hexadecimal
+	/// digits.
+	TruncatedUnicodeEscape,
+
+	/// `\U` escape sequence above `U+10FFFF`.
+	OutOfRangeUnicodeEscape,
+
+	/// `\u` or `\U` escape sequence designating a surrogate code point, which
+	/// cannot be represented by a `char`.
+	SurrogateUnicodeEscape,
+
+	/// `\N` escape sequence not followed by a character name between braces.
+	MalformedNameEscape,
+
+	/// Unknown character name in a `\N{...}` escape sequence.
+	///
+	/// Without the `unicode-names` feature, no character name is known.
+	UnknownName,
+}
+
+impl fmt::Display for PythonEscapeErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::UnsupportedPrefix => write!(f, "unsupported string prefix"),
+			Self::ExpectedLiteral => write!(f, "expected string literal"),
+			Self::MixedBytes => write!(f, "cannot mix bytes and nonbytes literals"),
+			Self::Unterminated => write!(f, "unterminated string literal"),
+			Self::UnescapedNewline => write!(f, "line break in single-quoted string literal"),
+			Self::NonAsciiCharInBytes(c) => {
+				write!(f, "non-ASCII character {c:?} in bytes literal")
+			}
+			Self::TruncatedHexEscape => write!(f, "truncated \\xXX escape"),
+			Self::TruncatedUnicodeEscape => write!(f, "truncated unicode escape"),
+			Self::OutOfRangeUnicodeEscape => write!(f, "illegal Unicode character"),
+			Self::SurrogateUnicodeEscape => write!(f, "surrogate unicode escape"),
+			Self::MalformedNameEscape => write!(f, "malformed \\N character escape"),
+			Self::UnknownName => write!(f, "unknown Unicode character name"),
+		}
+	}
+}
+
+/// Python literal escape error.
+///
+/// Offsets and lengths are expressed in the length unit of the decoded
+/// characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PythonEscapeError {
+	/// Kind of error.
+	kind: PythonEscapeErrorKind,
+
+	/// Offset of the invalid sequence.
+	offset: usize,
+
+	/// Length of the invalid sequence.
+	len: usize,
+}
+
+impl PythonEscapeError {
+	/// Returns the kind of error.
+	#[inline(always)]
+	pub fn kind(&self) -> PythonEscapeErrorKind {
+		self.kind
+	}
+
+	/// Returns the offset of the invalid sequence.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the length of the invalid sequence.
+	///
+	/// Like a decoded character, an error covers the prefixes, quotes and
+	/// separators preceding it. The invalid sequence of an escape error then
+	/// ends before the first offending character. An unterminated literal is
+	/// reported at the end of the input.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+}
+
+impl fmt::Display for PythonEscapeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at offset {}", self.kind, self.offset)
+	}
+}
+
+impl std::error::Error for PythonEscapeError {}
+
+impl<L: Length> SourceLen<L> for PythonEscapeError {
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		L::new(self.len)
+	}
+}
+
+/// Part of an implicitly concatenated literal.
+#[derive(Clone, Copy, Debug)]
+struct Part {
+	/// Quote character.
+	quote: char,
+
+	/// Whether or not the part is triple-quoted.
+	triple: bool,
+
+	/// Whether or not the part is a raw literal.
+	raw: bool,
+}
+
+/// Decoding state.
+#[derive(Clone, Copy, Debug)]
+enum State {
+	/// Before the prefix of the next part, if any.
+	Between,
+
+	/// Inside a part.
+	Part(Part),
+
+	/// After the end of the input.
+	Finished,
+}
+
+/// Decoder for Python string and bytes literals, over already decoded
+/// characters.
+///
+/// The input is the whole literal, including its prefix and quotes. It may
+/// be made of several implicitly concatenated parts, separated by
+/// whitespace, line continuations and comments. Prefixes, quotes and
+/// separators do not produce any character: their length is added to the
+/// length of the next decoded character (or error), or of the last one at
+/// the end of the literal. An empty literal yields nothing. Once the iterator
+/// is exhausted, [`offset`](Self::offset) is the end of the literal in every
+/// case.
+///
+/// Escape sequences are decoded into a single character whose length is the
+/// length of the whole sequence, in the length unit of the input characters.
+/// Bytes are decoded as the character with the same value (`\xff` is decoded
+/// as `U+00FF`), and octal escape sequences above `\377` are truncated to a
+/// byte in bytes literals. As in Python, a backslash that does not start an
+/// escape sequence is kept, and raw literals do not process escape sequences.
+/// Line breaks in triple-quoted literals are decoded as a single line feed.
+///
+/// `\N{...}` escape sequences require the `unicode-names` feature.
+///
+/// Decoding continues after an error, which covers the invalid sequence.
+///
+/// ```
+/// use decoded_char::{DecodedChars, PythonLiteralDecoded};
+///
+/// let literal = r#"'caf\xe9' "\t!" r'\n'"#;
+/// let decoded: Vec<_> = PythonLiteralDecoded::new(literal.decoded_chars())
+///     .map(|c| c.map(|c| (c.chr(), c.len().0)))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(
+///     decoded,
+///     [('c', 2), ('a', 1), ('f', 1), ('é', 4), ('\t', 5), ('!', 1), ('\\', 5), ('n', 2)]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct PythonLiteralDecoded<C: Iterator<Item = DecodedChar<L>>, L = ByteLen> {
+	/// Decoded characters of the literal.
+	chars: Lookahead<C>,
+
+	/// Offset of the first character.
+	start: usize,
+
+	/// Whether or not this is a bytes literal, once the first part is known.
+	bytes: Option<bool>,
+
+	/// Decoding state.
+	state: State,
+
+	/// Whether or not the next character follows a backslash in a raw
+	/// literal.
+	escaped: bool,
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> PythonLiteralDecoded<C, L> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self::with_start(chars, 0)
+	}
+
+	/// Creates a new decoder where the first character is at the given offset
+	/// in the source file.
+	#[inline(always)]
+	pub fn with_start(chars: C, offset: usize) -> Self {
+		Self {
+			chars: Lookahead::new(chars),
+			start: offset,
+			bytes: None,
+			state: State::Between,
+			escaped: false,
+		}
+	}
+
+	/// Checks if this is a bytes literal.
+	///
+	/// This is determined by the prefix of the first part, and is `false`
+	/// until it is decoded.
+	#[inline(always)]
+	pub fn is_bytes(&self) -> bool {
+		self.bytes == Some(true)
+	}
+
+	/// Returns the offset, in the source file, of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.start + self.chars.offset()
+	}
+
+	/// Creates an error covering the source from the given offset to the
+	/// current offset.
+	#[inline(always)]
+	fn error(&self, kind: PythonEscapeErrorKind, offset: usize) -> PythonEscapeError {
+		PythonEscapeError {
+			kind,
+			offset,
+			len: self.offset() - offset,
+		}
+	}
+
+	/// Returns the next character, without consuming it.
+	#[inline(always)]
+	fn peek(&mut self) -> Option<char> {
+		self.chars.peek().map(DecodedChar::chr)
+	}
+
+	/// Consumes the next character if it satisfies the given predicate.
+	#[inline(always)]
+	fn next_if(&mut self, f: impl FnOnce(char) -> bool) -> Option<char> {
+		let c = self.peek().filter(|c| f(*c))?;
+		self.chars.next();
+		Some(c)
+	}
+
+	/// Skips the whitespace, line continuations and comments separating two
+	/// parts.
+	fn skip_separators(&mut self) {
+		loop {
+			match self.peek() {
+				Some(' ' | '\t' | '\u{c}' | '\n' | '\r') => {
+					self.chars.next();
+				}
+				Some('\\')
+					if matches!(
+						self.chars.peek_nth(1).map(DecodedChar::chr),
+						Some('\n' | '\r')
+					) =>
+				{
+					self.chars.next();
+				}
+				Some('#') => while self.next_if(|c| !matches!(c, '\n' | '\r')).is_some() {},
+				_ => break,
+			}
+		}
+	}
+
+	/// Starts the next part, after its separators.
+	///
+	/// Returns `None` at the end of the input.
+	fn begin_part(&mut self) -> Option<Result<(), PythonEscapeErrorKind>> {
+		self.skip_separators();
+		self.peek()?;
+
+		let mut raw = false;
+		let mut bytes = false;
+		let mut unicode = false;
+		let mut supported = true;
+		let mut prefix_len = 0;
+		while let Some(c) = self.next_if(|c| c.is_ascii_alphabetic()) {
+			match c.to_ascii_lowercase() {
+				'r' if !raw => raw = true,
+				'b' if !bytes => bytes = true,
+				'u' if !unicode => unicode = true,
+				_ => supported = false,
+			}
+
+			prefix_len += 1
+		}
+
+		supported &= !unicode || prefix_len == 1;
+
+		let Some(quote) = self.next_if(|c| matches!(c, '\'' | '"')) else {
+			if prefix_len == 0 {
+				self.chars.next();
+			}
+
+			return Some(Err(PythonEscapeErrorKind::ExpectedLiteral));
+		};
+
+		let triple = self.chars.peek_nth(0).map(DecodedChar::chr) == Some(quote)
+			&& self.chars.peek_nth(1).map(DecodedChar::chr) == Some(quote);
+		if triple {
+			self.chars.next();
+			self.chars.next();
+		}
+
+		self.state = State::Part(Part { quote, triple, raw });
+
+		if !supported {
+			return Some(Err(PythonEscapeErrorKind::UnsupportedPrefix));
+		}
+
+		match self.bytes {
+			Some(b) if b != bytes => Some(Err(PythonEscapeErrorKind::MixedBytes)),
+			_ => {
+				self.bytes = Some(bytes);
+				Some(Ok(()))
+			}
+		}
+	}
+
+	/// Skips a line continuation, once its backslash is consumed.
+	///
+	/// Returns `false`, without consuming anything, if the backslash does not
+	/// start a line continuation.
+	fn continuation(&mut self) -> bool {
+		match self.peek() {
+			Some('\n') => {
+				self.chars.next();
+			}
+			Some('\r') => {
+				self.chars.next();
+				self.next_if(|c| c == '\n');
+			}
+			_ => return false,
+		}
+
+		true
+	}
+
+	/// Skips the line continuations, quotes, prefixes and separators ending
+	/// the literal, if any, so that their length is added to the last decoded
+	/// character.
+	fn trailing(&mut self) {
+		if let State::Part(part) = self.state {
+			if !matches!(self.peek(), Some(c) if c == part.quote || c == '\\') {
+				return;
+			}
+		}
+
+		let checkpoint = self.chars.checkpoint();
+		let (state, bytes) = (self.state, self.bytes);
+		loop {
+			match self.state {
+				State::Part(part) => match self.chars.next().map(|c| c.chr()) {
+					Some('\\') if !part.raw && self.continuation() => (),
+					Some(c) if c == part.quote && !self.escaped && self.close(part) => {
+						self.state = State::Between
+					}
+					_ => break,
+				},
+				State::Between => match self.begin_part() {
+					Some(Ok(())) => (),
+					Some(Err(_)) => break,
+					None => {
+						self.state = State::Finished;
+						return self.chars.commit(checkpoint);
+					}
+				},
+				State::Finished => return self.chars.commit(checkpoint),
+			}
+		}
+
+		self.chars.restore(checkpoint);
+		self.state = state;
+		self.bytes = bytes;
+	}
+
+	/// Decodes an escape sequence, once its backslash is consumed.
+	///
+	/// Returns `None` for a line continuation.
+	fn escape(&mut self) -> Option<Result<char, PythonEscapeErrorKind>> {
+		if self.continuation() {
+			return None;
+		}
+
+		let bytes = self.is_bytes();
+		let c = match self.peek() {
+			Some('\\') => '\\',
+			Some('\'') => '\'',
+			Some('"') => '"',
+			Some('a') => '\u{7}',
+			Some('b') => '\u{8}',
+			Some('f') => '\u{c}',
+			Some('n') => '\n',
+			Some('r') => '\r',
+			Some('t') => '\t',
+			Some('v') => '\u{b}',
+			Some('0'..='7') => return Some(Ok(self.octal_escape())),
+			Some('x') => {
+				self.chars.next();
+				return Some(
+					self.hex_digits(2, PythonEscapeErrorKind::TruncatedHexEscape)
+						.map(|value| char::from(value as u8)),
+				);
+			}
+			Some(e @ ('u' | 'U')) if !bytes => {
+				self.chars.next();
+				return Some(self.unicode_escape(if e == 'u' { 4 } else { 8 }));
+			}
+			Some('N') if !bytes => {
+				self.chars.next();
+				return Some(self.name_escape());
+			}
+			_ => return Some(Ok('\\')),
+		};
+
+		self.chars.next();
+		Some(Ok(c))
+	}
+
+	/// Decodes an octal escape sequence, once its backslash is consumed.
+	fn octal_escape(&mut self) -> char {
+		let mut value = 0;
+		for _ in 0..3 {
+			match self.next_if(|c| matches!(c, '0'..='7')) {
+				Some(c) => value = value * 8 + c.to_digit(8).unwrap(),
+				None => break,
+			}
+		}
+
+		if self.is_bytes() {
+			char::from(value as u8)
+		} else {
+			char::from_u32(value).unwrap()
+		}
+	}
+
+	/// Consumes `count` hexadecimal digits and returns their value.
+	fn hex_digits(
+		&mut self,
+		count: usize,
+		kind: PythonEscapeErrorKind,
+	) -> Result<u32, PythonEscapeErrorKind> {
+		let mut value = 0;
+		for _ in 0..count {
+			match self.next_if(|c| c.is_ascii_hexdigit()) {
+				Some(c) => value = value * 16 + c.to_digit(16).unwrap(),
+				None => return Err(kind),
+			}
+		}
+
+		Ok(value)
+	}
+
+	/// Decodes a `\u` or `\U` escape sequence with the given number of
+	/// digits, once its prefix is consumed.
+	fn unicode_escape(&mut self, count: usize) -> Result<char, PythonEscapeErrorKind> {
+		let value = self.hex_digits(count, PythonEscapeErrorKind::TruncatedUnicodeEscape)?;
+		char::from_u32(value).ok_or(if value > 0x10ffff {
+			PythonEscapeErrorKind::OutOfRangeUnicodeEscape
+		} else {
+			PythonEscapeErrorKind::SurrogateUnicodeEscape
+		})
+	}
+
+	/// Decodes a `\N{...}` escape sequence, once its `\N` prefix is consumed.
+	fn name_escape(&mut self) -> Result<char, PythonEscapeErrorKind> {
+		if self.next_if(|c| c == '{').is_none() {
+			return Err(PythonEscapeErrorKind::MalformedNameEscape);
+		}
+
+		let mut name = String::new();
+		while let Some(c) = self.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-')) {
+			name.push(c)
+		}
+
+		if name.is_empty() || self.next_if(|c| c == '}').is_none() {
+			return Err(PythonEscapeErrorKind::MalformedNameEscape);
+		}
+
+		lookup_name(&name).ok_or(PythonEscapeErrorKind::UnknownName)
+	}
+
+	/// Checks if the next characters close the given part, and consumes them,
+	/// once its first quote is consumed.
+	fn close(&mut self, part: Part) -> bool {
+		if part.triple {
+			let closed = self.chars.peek_nth(0).map(DecodedChar::chr) == Some(part.quote)
+				&& self.chars.peek_nth(1).map(DecodedChar::chr) == Some(part.quote);
+			if closed {
+				self.chars.next();
+				self.chars.next();
+			}
+
+			closed
+		} else {
+			true
+		}
+	}
+}
+
+/// Finds the character with the given name.
+#[cfg(feature = "unicode-names")]
+fn lookup_name(name: &str) -> Option<char> {
+	unicode_names2::character(name)
+}
+
+/// Finds the character with the given name.
+#[cfg(not(feature = "unicode-names"))]
+fn lookup_name(_name: &str) -> Option<char> {
+	None
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> Iterator for PythonLiteralDecoded<C, L> {
+	type Item = Result<DecodedChar<L>, PythonEscapeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let start = self.offset();
+		let result = loop {
+			let part = match self.state {
+				State::Between => match self.begin_part() {
+					Some(Ok(())) => continue,
+					Some(Err(kind)) => break Err(kind),
+					None => {
+						self.state = State::Finished;
+						return None;
+					}
+				},
+				State::Part(part) => part,
+				State::Finished => return None,
+			};
+
+			let Some(c) = self.chars.next().map(|c| c.chr()) else {
+				self.state = State::Finished;
+				break Err(PythonEscapeErrorKind::Unterminated);
+			};
+
+			let escaped = std::mem::take(&mut self.escaped);
+			break match c {
+				c if c == part.quote && !escaped && self.close(part) => {
+					self.state = State::Between;
+					continue;
+				}
+				'\\' if part.raw => {
+					self.escaped = !escaped;
+					Ok('\\')
+				}
+				'\\' => match self.escape() {
+					Some(result) => result,
+					None => continue,
+				},
+				'\n' | '\r' => {
+					if c == '\r' {
+						self.next_if(|c| c == '\n');
+					}
+
+					if part.triple || escaped {
+						Ok('\n')
+					} else {
+						Err(PythonEscapeErrorKind::UnescapedNewline)
+					}
+				}
+				c if !c.is_ascii() && self.is_bytes() => {
+					Err(PythonEscapeErrorKind::NonAsciiCharInBytes(c))
+				}
+				c => Ok(c),
+			};
+		};
+
+		self.trailing();
+		Some(match result {
+			Ok(c) => Ok(DecodedChar::new(c, L::new(self.offset() - start))),
+			Err(kind) => Err(self.error(kind, start)),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{DecodedChars, DecodedIterator};
+	use PythonEscapeErrorKind::*;
+
+	type Item = Result<(char, usize), (PythonEscapeErrorKind, usize, usize)>;
+
+	/// Decodes the given literal, checking that items cover the whole input
+	/// without gaps.
+	fn decode(input: &str) -> Vec<Item> {
+		let mut decoder = PythonLiteralDecoded::with_start(input.decoded_chars(), 1);
+		let mut items = Vec::new();
+		let mut offset = 1;
+		while let Some(item) = decoder.next() {
+			let item = item
+				.map(|c| (c.chr(), c.len().0))
+				.map_err(|e| (e.kind(), e.offset(), e.len()));
+			let len = match item {
+				Ok((_, len)) => len,
+				Err((_, error_offset, len)) => {
+					assert_eq!(error_offset, offset, "{input:?}");
+					len
+				}
+			};
+
+			offset += len;
+			assert_eq!(decoder.offset(), offset, "{input:?}");
+			items.push(item);
+		}
+
+		assert_eq!(decoder.offset(), 1 + input.len(), "{input:?}");
+		if !items.is_empty() {
+			assert_eq!(offset, 1 + input.len(), "{input:?}");
+		}
+
+		items
+	}
+
+	#[test]
+	fn escapes() {
+		assert_eq!(
+			decode(r#"'\a\b\f\n\r\t\v\\\'\"\101\x41\u0041\U0001F600\q'"#),
+			[
+				Ok(('\u{7}', 3)),
+				Ok(('\u{8}', 2)),
+				Ok(('\u{c}', 2)),
+				Ok(('\n', 2)),
+				Ok(('\r', 2)),
+				Ok(('\t', 2)),
+				Ok(('\u{b}', 2)),
+				Ok(('\\', 2)),
+				Ok(('\'', 2)),
+				Ok(('"', 2)),
+				Ok(('A', 4)),
+				Ok(('A', 4)),
+				Ok(('A', 6)),
+				Ok(('😀', 10)),
+				Ok(('\\', 1)),
+				Ok(('q', 2)),
+			]
+		);
+		assert_eq!(
+			decode(r"b'\xff\777'"),
+			[Ok(('\u{ff}', 6)), Ok(('\u{ff}', 5))]
+		);
+		assert_eq!(
+			decode(r"R'\n\''"),
+			[Ok(('\\', 3)), Ok(('n', 1)), Ok(('\\', 1)), Ok(('\'', 2)),]
+		);
+		assert_eq!(
+			decode("'''a\r\nb'''"),
+			[Ok(('a', 4)), Ok(('\n', 2)), Ok(('b', 4))]
+		);
+	}
+
+	#[test]
+	fn name_escapes() {
+		let expected = if cfg!(feature = "unicode-names") {
+			Ok(('é', 37))
+		} else {
+			Err((UnknownName, 1, 37))
+		};
+
+		assert_eq!(decode(r"'\N{LATIN SMALL LETTER E WITH ACUTE}'"), [expected]);
+		assert_eq!(
+			decode(r"'\N{}'"),
+			[Err((MalformedNameEscape, 1, 4)), Ok(('}', 2))]
+		);
+		assert_eq!(decode(r"b'\N'"), [Ok(('\\', 3)), Ok(('N', 2))]);
+	}
+
+	#[test]
+	fn concatenation() {
+		assert_eq!(
+			decode("'a' \\\n # comment\n u\"b\""),
+			[Ok(('a', 2)), Ok(('b', 20))]
+		);
+
+		// Quotes, separators and prefixes before an error belong to it.
+		assert_eq!(
+			decode("'a'  b'c'"),
+			[Ok(('a', 2)), Err((MixedBytes, 3, 5)), Ok(('c', 2))]
+		);
+		let offsets: Vec<_> = PythonLiteralDecoded::new("'a'  b'c'".decoded_chars())
+			.with_offsets()
+			.map(|item| {
+				item.map(|(offset, c)| (offset, c.chr()))
+					.map_err(|e| e.offset())
+			})
+			.collect();
+		assert_eq!(offsets, [Ok((0, 'a')), Err(2), Ok((7, 'c'))]);
+
+		// Line continuations and empty parts at the end belong to the last
+		// character.
+		assert_eq!(decode("'a\\\n' '' \"\"\"\"\"\""), [Ok(('a', 15))]);
+
+		// The length of an empty literal is only given by the final offset.
+		assert_eq!(decode("'' ''"), []);
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(
+			decode("f'a'"),
+			[Err((UnsupportedPrefix, 1, 2)), Ok(('a', 2))]
+		);
+		assert_eq!(
+			decode("ub'a'"),
+			[Err((UnsupportedPrefix, 1, 3)), Ok(('a', 2))]
+		);
+		assert_eq!(
+			decode("'a' x"),
+			[Ok(('a', 2)), Err((ExpectedLiteral, 3, 3))]
+		);
+		assert_eq!(decode("x"), [Err((ExpectedLiteral, 1, 1))]);
+		assert_eq!(
+			decode("'ab"),
+			[Ok(('a', 2)), Ok(('b', 1)), Err((Unterminated, 4, 0))]
+		);
+		assert_eq!(decode("'a' '"), [Ok(('a', 2)), Err((Unterminated, 3, 3))]);
+		assert_eq!(
+			decode("'a\nb'"),
+			[Ok(('a', 2)), Err((UnescapedNewline, 3, 1)), Ok(('b', 2))]
+		);
+		assert_eq!(decode("b'é'"), [Err((NonAsciiCharInBytes('é'), 1, 5))]);
+		assert_eq!(decode(r"'\x4'"), [Err((TruncatedHexEscape, 1, 5))]);
+		assert_eq!(decode(r"'\ud800'"), [Err((SurrogateUnicodeEscape, 1, 8))]);
+		assert_eq!(
+			decode(r"'\U00110000'"),
+			[Err((OutOfRangeUnicodeEscape, 1, 12))]
+		);
+		assert_eq!(decode(r"'\u12'"), [Err((TruncatedUnicodeEscape, 1, 6))]);
+
+		let e = PythonLiteralDecoded::new("b'a' 'b'".decoded_chars())
+			.nth(1)
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(
+			e.to_string(),
+			"cannot mix bytes and nonbytes literals at offset 3"
+		);
+	}
+
+	#[test]
+	fn lengths_add_up() {
+		let inputs = [
+			"'plain'",
+			"rb'\\x' Br\"\"\"\\\n\"\"\" b'\\x4' 'é' # end\n",
+			"'\\\n' '\\ud800'\n\n'''a\r\n'' x",
+			"'a' '\\N{' f\"b\" '\\",
+			"'' '\\\n' \"\"\"\"\"\" # end",
+		];
+
+		for input in inputs {
+			decode(input);
+		}
+	}
+}
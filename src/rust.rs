@@ -0,0 +1,641 @@
+use crate::{ByteLen, DecodedChar, Length, Lookahead, SourceLen};
+use std::fmt;
+
+/// Kind of Rust literal.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum RustLiteralKind {
+	/// Character literal (`'a'`).
+	Char,
+
+	/// Byte literal (`b'a'`).
+	Byte,
+
+	/// String literal (`"abc"`).
+	Str,
+
+	/// Byte string literal (`b"abc"`).
+	ByteStr,
+}
+
+impl RustLiteralKind {
+	/// Checks if this is a byte or byte string literal.
+	#[inline(always)]
+	pub fn is_byte(&self) -> bool {
+		matches!(self, Self::Byte | Self::ByteStr)
+	}
+
+	/// Checks if this literal must contain exactly one character.
+	#[inline(always)]
+	pub fn is_single(&self) -> bool {
+		matches!(self, Self::Char | Self::Byte)
+	}
+}
+
+/// Kind of Rust literal escape error.
+///
+/// These are the errors reported by `rustc` when unescaping literals.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RustEscapeErrorKind {
+	/// The literal ended right after a backslash.
+	LoneSlash,
+
+	/// Backslash followed by a character that does not start an escape
+	/// sequence.
+	InvalidEscape(char),
+
+	/// Carriage return that is not followed by a line feed.
+	BareCarriageReturn,
+
+	/// Character that must be escaped in a character or byte literal (quote,
+	/// tabulation, line feed or carriage return).
+	EscapeOnlyChar(char),
+
+	/// `\x` escape sequence with less than two digits.
+	TooShortHexEscape,
+
+	/// Invalid character in a `\x` escape sequence.
+	InvalidCharInHexEscape(char),
+
+	/// `\x` escape sequence above `\x7f` outside of a byte literal.
+	OutOfRangeHexEscape,
+
+	/// `\u` escape sequence not followed by `{`.
+	NoBraceInUnicodeEscape,
+
+	/// `\u{` escape sequence starting with an underscore.
+	LeadingUnderscoreUnicodeEscape,
+
+	/// `\u{` escape sequence with more than six digits.
+	OverlongUnicodeEscape,
+
+	/// `\u{` escape sequence without closing brace.
+	UnclosedUnicodeEscape,
+
+	/// Invalid character in a `\u{` escape sequence.
+	InvalidCharInUnicodeEscape(char),
+
+	/// `\u{}` escape sequence without digits.
+	EmptyUnicodeEscape,
+
+	/// `\u{` escape sequence designating a surrogate code point.
+	LoneSurrogateUnicodeEscape,
+
+	/// `\u{` escape sequence above `\u{10ffff}`.
+	OutOfRangeUnicodeEscape,
+
+	/// `\u{` escape sequence in a byte or byte string literal.
+	UnicodeEscapeInByte,
+
+	/// Non-ASCII character in a byte or byte string literal.
+	NonAsciiCharInByte(char),
+
+	/// Character or byte literal without character.
+	ZeroChars,
+
+	/// Character or byte literal with more than one character.
+	MoreThanOneChar,
+}
+
+impl fmt::Display for RustEscapeErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::LoneSlash => write!(f, "incomplete escape sequence"),
+			Self::InvalidEscape(c) => {
+				write!(f, "unknown character escape: `{}`", c.escape_debug())
+			}
+			Self::BareCarriageReturn => write!(f, "bare carriage return"),
+			Self::EscapeOnlyChar(c) => write!(f, "character {c:?} must be escaped"),
+			Self::TooShortHexEscape => write!(f, "numeric character escape is too short"),
+			Self::InvalidCharInHexEscape(c) => {
+				write!(f, "invalid character {c:?} in numeric character escape")
+			}
+			Self::OutOfRangeHexEscape => write!(f, "out of range hex escape"),
+			Self::NoBraceInUnicodeEscape => write!(f, "incorrect unicode escape sequence"),
+			Self::LeadingUnderscoreUnicodeEscape => write!(f, "invalid start of unicode escape"),
+			Self::OverlongUnicodeEscape => write!(f, "overlong unicode escape"),
+			Self::UnclosedUnicodeEscape => write!(f, "unterminated unicode escape"),
+			Self::InvalidCharInUnicodeEscape(c) => {
+				write!(f, "invalid character {c:?} in unicode escape")
+			}
+			Self::EmptyUnicodeEscape => write!(f, "empty unicode escape"),
+			Self::LoneSurrogateUnicodeEscape => {
+				write!(f, "invalid unicode character escape: surrogate")
+			}
+			Self::OutOfRangeUnicodeEscape => {
+				write!(f, "invalid unicode character escape: out of range")
+			}
+			Self::UnicodeEscapeInByte => write!(f, "unicode escape in byte literal"),
+			Self::NonAsciiCharInByte(c) => write!(f, "non-ASCII character {c:?} in byte literal"),
+			Self::ZeroChars => write!(f, "empty character literal"),
+			Self::MoreThanOneChar => write!(f, "character literal may only contain one codepoint"),
+		}
+	}
+}
+
+/// Rust literal escape error.
+///
+/// Offsets and lengths are expressed in the length unit of the decoded
+/// characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RustEscapeError {
+	/// Kind of error.
+	kind: RustEscapeErrorKind,
+
+	/// Offset of the invalid sequence.
+	offset: usize,
+
+	/// Length of the invalid sequence.
+	len: usize,
+}
+
+impl RustEscapeError {
+	/// Returns the kind of error.
+	#[inline(always)]
+	pub fn kind(&self) -> RustEscapeErrorKind {
+		self.kind
+	}
+
+	/// Returns the offset of the invalid sequence.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the length of the invalid sequence.
+	///
+	/// The invalid sequence starts right after the previous character (so it
+	/// includes any preceding line continuation) and ends with the offending
+	/// character, if any. For [`RustEscapeErrorKind::MoreThanOneChar`], it
+	/// covers all the extra characters.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+}
+
+impl fmt::Display for RustEscapeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at offset {}", self.kind, self.offset)
+	}
+}
+
+impl std::error::Error for RustEscapeError {}
+
+impl<L: Length> SourceLen<L> for RustEscapeError {
+	#[inline(always)]
+	fn source_len(&self) -> L {
+		L::new(self.len)
+	}
+}
+
+/// Decoder for the content of a Rust character, byte, string or byte string
+/// literal (without its quotes), over already decoded characters.
+///
+/// Escape sequences are decoded into a single character whose length is the
+/// length of the whole sequence, in the length unit of the input characters.
+/// Bytes are decoded as the character with the same value (`\xff` is decoded
+/// as `U+00FF`).
+///
+/// In (byte) string literals, a line continuation (a backslash followed by a
+/// line break and whitespace) does not produce any character: its length is
+/// added to the length of the next decoded character (or error), or of the
+/// last one at the end of the literal. A carriage return followed by a line
+/// feed is decoded as a single line feed.
+///
+/// A literal made only of line continuations yields nothing. Once the
+/// iterator is exhausted, [`offset`](Self::offset) is the end of the literal
+/// in every case.
+///
+/// Decoding continues after an error, which covers the invalid sequence.
+///
+/// ```
+/// use decoded_char::{DecodedChars, RustLiteralDecoded, RustLiteralKind};
+///
+/// let literal = r"\x41\u{1F600}\n";
+/// let decoded: Vec<_> = RustLiteralDecoded::new(literal.decoded_chars(), RustLiteralKind::Str)
+///     .map(|c| c.map(|c| (c.chr(), c.len().0)))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(decoded, [('A', 4), ('😀', 9), ('\n', 2)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RustLiteralDecoded<C: Iterator<Item = DecodedChar<L>>, L = ByteLen> {
+	/// Decoded characters of the literal.
+	chars: Lookahead<C>,
+
+	/// Kind of literal.
+	kind: RustLiteralKind,
+
+	/// Offset of the first character.
+	start: usize,
+
+	/// Number of decoded characters (and errors).
+	count: usize,
+
+	/// Whether or not the end of the literal was reached.
+	finished: bool,
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> RustLiteralDecoded<C, L> {
+	#[inline(always)]
+	pub fn new(chars: C, kind: RustLiteralKind) -> Self {
+		Self::with_start(chars, kind, 0)
+	}
+
+	/// Creates a new decoder where the first character is at the given offset
+	/// in the source file.
+	#[inline(always)]
+	pub fn with_start(chars: C, kind: RustLiteralKind, offset: usize) -> Self {
+		Self {
+			chars: Lookahead::new(chars),
+			kind,
+			start: offset,
+			count: 0,
+			finished: false,
+		}
+	}
+
+	/// Returns the kind of literal.
+	#[inline(always)]
+	pub fn kind(&self) -> RustLiteralKind {
+		self.kind
+	}
+
+	/// Returns the offset, in the source file, of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.start + self.chars.offset()
+	}
+
+	/// Creates an error covering the source from the given offset to the
+	/// current offset.
+	#[inline(always)]
+	fn error(&self, kind: RustEscapeErrorKind, offset: usize) -> RustEscapeError {
+		RustEscapeError {
+			kind,
+			offset,
+			len: self.offset() - offset,
+		}
+	}
+
+	/// Skips a line continuation, once its backslash is consumed.
+	///
+	/// Returns `false`, without consuming anything, if the backslash does not
+	/// start a line continuation.
+	fn continuation(&mut self) -> bool {
+		let line_break = match self.chars.peek().map(DecodedChar::chr) {
+			Some('\n') => true,
+			Some('\r') => self.chars.peek_nth(1).map(DecodedChar::chr) == Some('\n'),
+			_ => false,
+		};
+
+		if self.kind.is_single() || !line_break {
+			return false;
+		}
+
+		while self
+			.chars
+			.peek()
+			.is_some_and(|c| matches!(c.chr(), ' ' | '\t' | '\n' | '\r'))
+		{
+			self.chars.next();
+		}
+
+		true
+	}
+
+	/// Skips the line continuations ending the literal, if any, so that their
+	/// length is added to the last decoded character.
+	fn trailing_continuations(&mut self) {
+		if self.chars.peek().map(DecodedChar::chr) != Some('\\') {
+			return;
+		}
+
+		let checkpoint = self.chars.checkpoint();
+		loop {
+			match self.chars.next().map(|c| c.chr()) {
+				None => break self.chars.commit(checkpoint),
+				Some('\\') if self.continuation() => (),
+				Some(_) => break self.chars.restore(checkpoint),
+			}
+		}
+	}
+
+	/// Decodes an escape sequence, once its backslash is consumed.
+	///
+	/// Returns `None` for a line continuation.
+	fn escape(&mut self) -> Option<Result<char, RustEscapeErrorKind>> {
+		if self.continuation() {
+			return None;
+		}
+
+		let Some(e) = self.chars.next() else {
+			return Some(Err(RustEscapeErrorKind::LoneSlash));
+		};
+
+		let c = match e.chr() {
+			'n' => '\n',
+			'r' => '\r',
+			't' => '\t',
+			'\\' => '\\',
+			'0' => '\0',
+			'\'' => '\'',
+			'"' => '"',
+			'x' => return Some(self.hex_escape()),
+			'u' => return Some(self.unicode_escape()),
+			c => return Some(Err(RustEscapeErrorKind::InvalidEscape(c))),
+		};
+
+		Some(Ok(c))
+	}
+
+	/// Decodes a `\x` escape sequence, once its `\x` prefix is consumed.
+	fn hex_escape(&mut self) -> Result<char, RustEscapeErrorKind> {
+		let mut value = 0;
+		for _ in 0..2 {
+			let c = self
+				.chars
+				.next()
+				.ok_or(RustEscapeErrorKind::TooShortHexEscape)?
+				.chr();
+			let digit = c
+				.to_digit(16)
+				.ok_or(RustEscapeErrorKind::InvalidCharInHexEscape(c))?;
+			value = value * 16 + digit;
+		}
+
+		if value > 0x7f && !self.kind.is_byte() {
+			return Err(RustEscapeErrorKind::OutOfRangeHexEscape);
+		}
+
+		Ok(char::from(value as u8))
+	}
+
+	/// Decodes a `\u` escape sequence, once its `\u` prefix is consumed.
+	fn unicode_escape(&mut self) -> Result<char, RustEscapeErrorKind> {
+		let next = |this: &mut Self| this.chars.next().map(|c| c.chr());
+
+		if next(self) != Some('{') {
+			return Err(RustEscapeErrorKind::NoBraceInUnicodeEscape);
+		}
+
+		let mut value = match next(self) {
+			None => return Err(RustEscapeErrorKind::UnclosedUnicodeEscape),
+			Some('_') => return Err(RustEscapeErrorKind::LeadingUnderscoreUnicodeEscape),
+			Some('}') => return Err(RustEscapeErrorKind::EmptyUnicodeEscape),
+			Some(c) => c
+				.to_digit(16)
+				.ok_or(RustEscapeErrorKind::InvalidCharInUnicodeEscape(c))?,
+		};
+
+		let mut digits = 1;
+		loop {
+			match next(self) {
+				None => return Err(RustEscapeErrorKind::UnclosedUnicodeEscape),
+				Some('_') => (),
+				Some('}') => break,
+				Some(c) => {
+					let digit = c
+						.to_digit(16)
+						.ok_or(RustEscapeErrorKind::InvalidCharInUnicodeEscape(c))?;
+
+					digits += 1;
+					if digits <= 6 {
+						value = value * 16 + digit
+					}
+				}
+			}
+		}
+
+		if digits > 6 {
+			Err(RustEscapeErrorKind::OverlongUnicodeEscape)
+		} else if self.kind.is_byte() {
+			Err(RustEscapeErrorKind::UnicodeEscapeInByte)
+		} else {
+			match char::from_u32(value) {
+				Some(c) => Ok(c),
+				None if value > 0x10ffff => Err(RustEscapeErrorKind::OutOfRangeUnicodeEscape),
+				None => Err(RustEscapeErrorKind::LoneSurrogateUnicodeEscape),
+			}
+		}
+	}
+
+	/// Decodes the next character, skipping line continuations.
+	fn decode(&mut self) -> Option<Result<char, RustEscapeErrorKind>> {
+		loop {
+			let c = self.chars.next()?.chr();
+			let kind = match c {
+				'\\' => match self.escape() {
+					Some(result) => return Some(result),
+					None => continue,
+				},
+				'\r' if !self.kind.is_single()
+					&& self.chars.peek().map(DecodedChar::chr) == Some('\n') =>
+				{
+					self.chars.next();
+					return Some(Ok('\n'));
+				}
+				'\r' if !self.kind.is_single() => RustEscapeErrorKind::BareCarriageReturn,
+				'\'' | '\t' | '\n' | '\r' if self.kind.is_single() => {
+					RustEscapeErrorKind::EscapeOnlyChar(c)
+				}
+				c if !c.is_ascii() && self.kind.is_byte() => {
+					RustEscapeErrorKind::NonAsciiCharInByte(c)
+				}
+				c => return Some(Ok(c)),
+			};
+
+			return Some(Err(kind));
+		}
+	}
+}
+
+impl<L: Length, C: Iterator<Item = DecodedChar<L>>> Iterator for RustLiteralDecoded<C, L> {
+	type Item = Result<DecodedChar<L>, RustEscapeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let start = self.offset();
+		let Some(result) = self.decode() else {
+			if self.kind.is_single() && self.count == 0 && !self.finished {
+				self.finished = true;
+				return Some(Err(self.error(RustEscapeErrorKind::ZeroChars, start)));
+			}
+
+			return None;
+		};
+
+		self.count += 1;
+		if self.kind.is_single() && self.count > 1 {
+			// A single error covers all the extra characters.
+			while self.decode().is_some() {}
+			return Some(Err(self.error(RustEscapeErrorKind::MoreThanOneChar, start)));
+		}
+
+		self.trailing_continuations();
+		Some(match result {
+			Ok(c) => Ok(DecodedChar::new(c, L::new(self.offset() - start))),
+			Err(kind) => Err(self.error(kind, start)),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DecodedChars;
+	use RustEscapeErrorKind::*;
+	use RustLiteralKind::*;
+
+	type Item = Result<(char, usize), (RustEscapeErrorKind, usize, usize)>;
+
+	/// Decodes the given literal content, checking that items cover the
+	/// whole input without gaps.
+	fn decode(input: &str, kind: RustLiteralKind) -> Vec<Item> {
+		let mut decoder = RustLiteralDecoded::with_start(input.decoded_chars(), kind, 1);
+		let mut items = Vec::new();
+		let mut offset = 1;
+		while let Some(item) = decoder.next() {
+			let item = item
+				.map(|c| (c.chr(), c.len().0))
+				.map_err(|e| (e.kind(), e.offset(), e.len()));
+			let len = match item {
+				Ok((_, len)) => len,
+				Err((_, error_offset, len)) => {
+					assert_eq!(error_offset, offset, "{input:?}");
+					len
+				}
+			};
+
+			offset += len;
+			assert_eq!(decoder.offset(), offset, "{input:?}");
+			items.push(item);
+		}
+
+		assert_eq!(decoder.offset(), 1 + input.len(), "{input:?}");
+		if !items.is_empty() {
+			assert_eq!(offset, 1 + input.len(), "{input:?}");
+		}
+
+		items
+	}
+
+	#[test]
+	fn escapes() {
+		assert_eq!(
+			decode(r#"\x41\u{1F6_00}\n\r\t\0\'\"\\é"#, Str),
+			[
+				Ok(('A', 4)),
+				Ok(('😀', 10)),
+				Ok(('\n', 2)),
+				Ok(('\r', 2)),
+				Ok(('\t', 2)),
+				Ok(('\0', 2)),
+				Ok(('\'', 2)),
+				Ok(('"', 2)),
+				Ok(('\\', 2)),
+				Ok(('é', 2)),
+			]
+		);
+		assert_eq!(decode(r"\xff", ByteStr), [Ok(('\u{ff}', 4))]);
+		assert_eq!(
+			decode("a\r\nb", Str),
+			[Ok(('a', 1)), Ok(('\n', 2)), Ok(('b', 1))]
+		);
+	}
+
+	#[test]
+	fn continuations() {
+		assert_eq!(decode("a\\\n  \tb", Str), [Ok(('a', 1)), Ok(('b', 6))]);
+		assert_eq!(decode("a\\\r\n\n b", Str), [Ok(('a', 1)), Ok(('b', 6))]);
+		// Trailing continuations are added to the last character.
+		assert_eq!(decode("a\\\n \\\n", Str), [Ok(('a', 6))]);
+		assert_eq!(
+			decode("a\\\n\\", Str),
+			[Ok(('a', 1)), Err((LoneSlash, 2, 3))]
+		);
+
+		// The length of a literal made only of line continuations is only
+		// given by the final offset.
+		assert_eq!(decode("\\\n ", Str), []);
+	}
+
+	#[test]
+	fn errors() {
+		// Errors cover the line continuations before them.
+		assert_eq!(decode("\\\n\\q", Str), [Err((InvalidEscape('q'), 1, 4))]);
+		assert_eq!(
+			decode(r"\x4g\xff", Str),
+			[
+				Err((InvalidCharInHexEscape('g'), 1, 4)),
+				Err((OutOfRangeHexEscape, 5, 4)),
+			]
+		);
+		assert_eq!(decode(r"\x4", Str), [Err((TooShortHexEscape, 1, 3))]);
+
+		let cases = [
+			(r"\u41", NoBraceInUnicodeEscape, 3),
+			(r"\u{_1}", LeadingUnderscoreUnicodeEscape, 4),
+			(r"\u{}", EmptyUnicodeEscape, 4),
+			(r"\u{1234567}", OverlongUnicodeEscape, 11),
+			(r"\u{41", UnclosedUnicodeEscape, 5),
+			(r"\u{4g}", InvalidCharInUnicodeEscape('g'), 5),
+			(r"\u{d800}", LoneSurrogateUnicodeEscape, 8),
+			(r"\u{110000}", OutOfRangeUnicodeEscape, 10),
+			("\r", BareCarriageReturn, 1),
+		];
+
+		for (input, kind, len) in cases {
+			assert_eq!(decode(input, Str)[0], Err((kind, 1, len)), "{input:?}");
+		}
+
+		assert_eq!(
+			decode(r"\u{41}", ByteStr),
+			[Err((UnicodeEscapeInByte, 1, 6))]
+		);
+		assert_eq!(decode("é", ByteStr), [Err((NonAsciiCharInByte('é'), 1, 2))]);
+
+		let e = RustLiteralDecoded::new(r"\q".decoded_chars(), Str)
+			.next()
+			.unwrap()
+			.unwrap_err();
+		assert_eq!(e.to_string(), "unknown character escape: `q` at offset 0");
+	}
+
+	#[test]
+	fn single() {
+		assert_eq!(decode(r"\u{e9}", Char), [Ok(('é', 6))]);
+		assert_eq!(decode("", Char), [Err((ZeroChars, 1, 0))]);
+		assert_eq!(decode("\n", Byte), [Err((EscapeOnlyChar('\n'), 1, 1))]);
+		assert_eq!(decode("\\\n", Char), [Err((InvalidEscape('\n'), 1, 2))]);
+		// A single error covers all the extra characters.
+		assert_eq!(
+			decode(r"ab\nc", Char),
+			[Ok(('a', 1)), Err((MoreThanOneChar, 2, 4))]
+		);
+		assert_eq!(
+			decode(r"\q\x", Byte),
+			[
+				Err((InvalidEscape('q'), 1, 2)),
+				Err((MoreThanOneChar, 3, 2))
+			]
+		);
+	}
+
+	#[test]
+	fn lengths_add_up() {
+		let inputs = [
+			"plain",
+			"\\\n \\x\\\n\\u{41}\\\n",
+			"é\\u{d800}\r\\\r\n\\",
+			"\\u{1F600\\x7f\\u{_}\\\n\t\t",
+			"\\\n \\\r\n",
+		];
+
+		for input in inputs {
+			for kind in [Char, Byte, Str, ByteStr] {
+				decode(input, kind);
+			}
+		}
+	}
+}
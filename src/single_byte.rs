@@ -0,0 +1,424 @@
+use crate::{ByteLen, DecodedChar};
+use std::fmt;
+
+#[rustfmt::skip]
+mod tables;
+
+/// Single-byte legacy encoding.
+///
+/// All these encodings are ASCII-compatible: bytes `0x00` to `0x7F` are
+/// decoded as the corresponding ASCII characters.
+///
+/// The upper half is decoded with the index tables of the WHATWG Encoding
+/// Standard, where the bytes Windows code pages leave undefined are mapped to
+/// the corresponding C1 control characters. ISO-8859-1, ISO-8859-9 and
+/// ISO-8859-11, which the standard folds into Windows-1252, Windows-1254 and
+/// Windows-874, are decoded with their ISO/IEC mappings.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum SingleByteEncoding {
+	/// ISO-8859-1 (Latin-1, Western European).
+	Iso8859_1,
+
+	/// ISO-8859-2 (Latin-2, Central European).
+	Iso8859_2,
+
+	/// ISO-8859-3 (Latin-3, South European).
+	Iso8859_3,
+
+	/// ISO-8859-4 (Latin-4, North European).
+	Iso8859_4,
+
+	/// ISO-8859-5 (Cyrillic).
+	Iso8859_5,
+
+	/// ISO-8859-6 (Arabic).
+	Iso8859_6,
+
+	/// ISO-8859-7 (Greek).
+	Iso8859_7,
+
+	/// ISO-8859-8 (Hebrew).
+	Iso8859_8,
+
+	/// ISO-8859-9 (Latin-5, Turkish).
+	Iso8859_9,
+
+	/// ISO-8859-10 (Latin-6, Nordic).
+	Iso8859_10,
+
+	/// ISO-8859-11 (Thai).
+	Iso8859_11,
+
+	/// ISO-8859-13 (Latin-7, Baltic Rim).
+	Iso8859_13,
+
+	/// ISO-8859-14 (Latin-8, Celtic).
+	Iso8859_14,
+
+	/// ISO-8859-15 (Latin-9, Western European with euro sign).
+	Iso8859_15,
+
+	/// ISO-8859-16 (Latin-10, South-Eastern European).
+	Iso8859_16,
+
+	/// Windows-874 (Thai).
+	Windows874,
+
+	/// Windows-1250 (Central European).
+	Windows1250,
+
+	/// Windows-1251 (Cyrillic).
+	Windows1251,
+
+	/// Windows-1252 (Western European).
+	Windows1252,
+
+	/// Windows-1253 (Greek).
+	Windows1253,
+
+	/// Windows-1254 (Turkish).
+	Windows1254,
+
+	/// Windows-1255 (Hebrew).
+	Windows1255,
+
+	/// Windows-1256 (Arabic).
+	Windows1256,
+
+	/// Windows-1257 (Baltic).
+	Windows1257,
+
+	/// Windows-1258 (Vietnamese).
+	Windows1258,
+
+	/// KOI8-R (Russian).
+	Koi8R,
+
+	/// KOI8-U (Ukrainian), including the Belarusian letters `ў` and `Ў` of
+	/// KOI8-RU.
+	Koi8U,
+
+	/// Mac OS Roman.
+	MacRoman,
+
+	/// Mac OS Cyrillic (including the Ukrainian letters).
+	MacCyrillic,
+
+	/// IBM code page 866 (DOS Cyrillic).
+	Ibm866,
+
+	/// `x-user-defined`, mapping the upper half to the Private Use Area
+	/// (`U+F780` to `U+F7FF`), as defined by the WHATWG Encoding Standard.
+	XUserDefined,
+}
+
+impl SingleByteEncoding {
+	/// Returns the name of the encoding.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Iso8859_1 => "ISO-8859-1",
+			Self::Iso8859_2 => "ISO-8859-2",
+			Self::Iso8859_3 => "ISO-8859-3",
+			Self::Iso8859_4 => "ISO-8859-4",
+			Self::Iso8859_5 => "ISO-8859-5",
+			Self::Iso8859_6 => "ISO-8859-6",
+			Self::Iso8859_7 => "ISO-8859-7",
+			Self::Iso8859_8 => "ISO-8859-8",
+			Self::Iso8859_9 => "ISO-8859-9",
+			Self::Iso8859_10 => "ISO-8859-10",
+			Self::Iso8859_11 => "ISO-8859-11",
+			Self::Iso8859_13 => "ISO-8859-13",
+			Self::Iso8859_14 => "ISO-8859-14",
+			Self::Iso8859_15 => "ISO-8859-15",
+			Self::Iso8859_16 => "ISO-8859-16",
+			Self::Windows874 => "windows-874",
+			Self::Windows1250 => "windows-1250",
+			Self::Windows1251 => "windows-1251",
+			Self::Windows1252 => "windows-1252",
+			Self::Windows1253 => "windows-1253",
+			Self::Windows1254 => "windows-1254",
+			Self::Windows1255 => "windows-1255",
+			Self::Windows1256 => "windows-1256",
+			Self::Windows1257 => "windows-1257",
+			Self::Windows1258 => "windows-1258",
+			Self::Koi8R => "KOI8-R",
+			Self::Koi8U => "KOI8-U",
+			Self::MacRoman => "macintosh",
+			Self::MacCyrillic => "x-mac-cyrillic",
+			Self::Ibm866 => "IBM866",
+			Self::XUserDefined => "x-user-defined",
+		}
+	}
+
+	/// Returns the mapping of the upper half of the encoding.
+	fn table(&self) -> &'static [u16; 128] {
+		match self {
+			Self::Iso8859_1 => &tables::ISO_8859_1,
+			Self::Iso8859_2 => &tables::ISO_8859_2,
+			Self::Iso8859_3 => &tables::ISO_8859_3,
+			Self::Iso8859_4 => &tables::ISO_8859_4,
+			Self::Iso8859_5 => &tables::ISO_8859_5,
+			Self::Iso8859_6 => &tables::ISO_8859_6,
+			Self::Iso8859_7 => &tables::ISO_8859_7,
+			Self::Iso8859_8 => &tables::ISO_8859_8,
+			Self::Iso8859_9 => &tables::ISO_8859_9,
+			Self::Iso8859_10 => &tables::ISO_8859_10,
+			Self::Iso8859_11 => &tables::ISO_8859_11,
+			Self::Iso8859_13 => &tables::ISO_8859_13,
+			Self::Iso8859_14 => &tables::ISO_8859_14,
+			Self::Iso8859_15 => &tables::ISO_8859_15,
+			Self::Iso8859_16 => &tables::ISO_8859_16,
+			Self::Windows874 => &tables::WINDOWS_874,
+			Self::Windows1250 => &tables::WINDOWS_1250,
+			Self::Windows1251 => &tables::WINDOWS_1251,
+			Self::Windows1252 => &tables::WINDOWS_1252,
+			Self::Windows1253 => &tables::WINDOWS_1253,
+			Self::Windows1254 => &tables::WINDOWS_1254,
+			Self::Windows1255 => &tables::WINDOWS_1255,
+			Self::Windows1256 => &tables::WINDOWS_1256,
+			Self::Windows1257 => &tables::WINDOWS_1257,
+			Self::Windows1258 => &tables::WINDOWS_1258,
+			Self::Koi8R => &tables::KOI8_R,
+			Self::Koi8U => &tables::KOI8_U,
+			Self::MacRoman => &tables::MAC_ROMAN,
+			Self::MacCyrillic => &tables::MAC_CYRILLIC,
+			Self::Ibm866 => &tables::IBM866,
+			Self::XUserDefined => &tables::X_USER_DEFINED,
+		}
+	}
+
+	/// Decodes the given byte.
+	///
+	/// Returns `None` if the byte is not mapped to any character.
+	#[inline(always)]
+	pub fn decode_byte(&self, b: u8) -> Option<char> {
+		if b < 0x80 {
+			Some(b as char)
+		} else {
+			match self.table()[(b - 0x80) as usize] {
+				0 => None,
+				c => char::from_u32(c as u32),
+			}
+		}
+	}
+}
+
+impl fmt::Display for SingleByteEncoding {
+	#[inline(always)]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.name().fmt(f)
+	}
+}
+
+/// Byte not mapped to any character by a single-byte encoding.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct UnmappedByte {
+	/// Byte offset in the encoded source file.
+	offset: usize,
+
+	/// Unmapped byte.
+	byte: u8,
+}
+
+impl UnmappedByte {
+	/// Returns the byte offset of the unmapped byte in the encoded source
+	/// file.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the unmapped byte.
+	#[inline(always)]
+	pub fn byte(&self) -> u8 {
+		self.byte
+	}
+}
+
+impl fmt::Display for UnmappedByte {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"unmapped byte {:#04x} at offset {}",
+			self.byte, self.offset
+		)
+	}
+}
+
+impl std::error::Error for UnmappedByte {}
+
+/// Decoder for bytes encoded with a single-byte legacy encoding.
+///
+/// Every decoded character has a length of 1 byte. Unmapped bytes are
+/// reported as errors.
+#[derive(Clone, Debug)]
+pub struct SingleByteDecoded<B> {
+	/// Encoded bytes.
+	bytes: B,
+
+	/// Encoding.
+	encoding: SingleByteEncoding,
+
+	/// Byte offset of the next byte.
+	offset: usize,
+}
+
+impl<B> SingleByteDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: SingleByteEncoding) -> Self {
+		Self::with_start(bytes, encoding, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	#[inline(always)]
+	pub fn with_start(bytes: B, encoding: SingleByteEncoding, offset: usize) -> Self {
+		Self {
+			bytes,
+			encoding,
+			offset,
+		}
+	}
+
+	/// Returns the encoding.
+	#[inline(always)]
+	pub fn encoding(&self) -> SingleByteEncoding {
+		self.encoding
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Turns this decoder into a lossy decoder, replacing unmapped bytes with
+	/// `U+FFFD REPLACEMENT CHARACTER`.
+	#[inline(always)]
+	pub fn lossy(self) -> LossySingleByteDecoded<B> {
+		LossySingleByteDecoded(self)
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for SingleByteDecoded<B> {
+	type Item = Result<DecodedChar, UnmappedByte>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.bytes.next().map(|byte| {
+			let offset = self.offset;
+			self.offset += 1;
+			match self.encoding.decode_byte(byte) {
+				Some(c) => Ok(DecodedChar::new(c, ByteLen(1))),
+				None => Err(UnmappedByte { offset, byte }),
+			}
+		})
+	}
+}
+
+/// Lossy decoder for bytes encoded with a single-byte legacy encoding.
+///
+/// Behaves like [`SingleByteDecoded`], but replaces unmapped bytes with a
+/// `U+FFFD REPLACEMENT CHARACTER` of length 1.
+#[derive(Clone, Debug)]
+pub struct LossySingleByteDecoded<B>(SingleByteDecoded<B>);
+
+impl<B> LossySingleByteDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, encoding: SingleByteEncoding) -> Self {
+		Self(SingleByteDecoded::new(bytes, encoding))
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.0.offset()
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for LossySingleByteDecoded<B> {
+	type Item = DecodedChar;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|result| {
+			result.unwrap_or_else(|_| DecodedChar::new(char::REPLACEMENT_CHARACTER, ByteLen(1)))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Decodes the given bytes lossily.
+	fn decode(bytes: &[u8], encoding: SingleByteEncoding) -> String {
+		SingleByteDecoded::new(bytes.iter().copied(), encoding)
+			.lossy()
+			.map(|c| c.chr())
+			.collect()
+	}
+
+	#[test]
+	fn known_answers() {
+		use SingleByteEncoding::*;
+		let cases: [(_, &[u8], &str); 31] = [
+			(Iso8859_1, b"\x80\xe9\xff", "\u{80}éÿ"),
+			(Iso8859_2, b"\xa1\xb1\xe8", "Ąąč"),
+			(Iso8859_3, b"\xa1\xc5\xfe", "ĦĊŝ"),
+			(Iso8859_4, b"\xa2\xbd\xf2", "ĸŊō"),
+			(Iso8859_5, b"\xb0\xef\xf0", "Ая№"),
+			(Iso8859_6, b"\xac\xc7\xf2", "،اْ"),
+			(Iso8859_7, b"\xa4\xc1\xf9", "€Αω"),
+			(Iso8859_8, b"\xaa\xe0\xfa", "×את"),
+			(Iso8859_9, b"\xd0\xdd\xfe", "Ğİş"),
+			(Iso8859_10, b"\xa1\xbd\xff", "Ą―ĸ"),
+			(Iso8859_11, b"\xa1\xdf\xfb", "ก฿๛"),
+			(Iso8859_13, b"\xa1\xc0\xff", "”Ą’"),
+			(Iso8859_14, b"\xa1\xd0\xfe", "ḂŴŷ"),
+			(Iso8859_15, b"\xa4\xbc\xbe", "€ŒŸ"),
+			(Iso8859_16, b"\xa1\xaa\xfe", "ĄȘț"),
+			(Windows874, b"\x80\x81\xa1", "€\u{81}ก"),
+			(Windows1250, b"\x8a\x98\xf8", "Š\u{98}ř"),
+			(Windows1251, b"\x80\x98\xff", "Ђ\u{98}я"),
+			(
+				Windows1252,
+				b"\x80\x81\x8d\x8f\x90\x9d\x9f",
+				"€\u{81}\u{8d}\u{8f}\u{90}\u{9d}Ÿ",
+			),
+			(Windows1253, b"\xaa\xc1\xd2", "\u{fffd}Α\u{fffd}"),
+			(Windows1254, b"\x8e\xd0\xfd", "\u{8e}Ğı"),
+			(Windows1255, b"\xca\xe0\xd9", "\u{5ba}א\u{fffd}"),
+			(Windows1256, b"\x81\xc7\xff", "پاے"),
+			(Windows1257, b"\xa1\xc0\xff", "\u{fffd}Ą˙"),
+			(Windows1258, b"\xc3\xd2\xfe", "Ă\u{309}₫"),
+			(Koi8R, b"\xc1\xe1\xb3", "аАЁ"),
+			(Koi8U, b"\xa4\xae\xbe", "єўЎ"),
+			(MacRoman, b"\x80\xa5\xdb", "Ä•€"),
+			(MacCyrillic, b"\x80\xa2\xff", "АҐ€"),
+			(Ibm866, b"\x80\xb0\xf0", "А░Ё"),
+			(XUserDefined, b"\x80\xff", "\u{f780}\u{f7ff}"),
+		];
+
+		for (encoding, bytes, expected) in cases {
+			assert_eq!(decode(bytes, encoding), expected, "{encoding}");
+		}
+	}
+
+	#[test]
+	fn unmapped() {
+		let results: Vec<_> = SingleByteDecoded::with_start(
+			b"a\xd2".iter().copied(),
+			SingleByteEncoding::Windows1253,
+			5,
+		)
+		.collect();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].as_ref().unwrap().chr(), 'a');
+		let e = *results[1].as_ref().unwrap_err();
+		assert_eq!((e.offset(), e.byte()), (6, 0xd2));
+		assert_eq!(e.to_string(), "unmapped byte 0xd2 at offset 6");
+	}
+}
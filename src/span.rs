@@ -0,0 +1,245 @@
+use crate::{DecodedItem, Length};
+use std::ops::Range;
+
+/// Span in the encoded source file.
+///
+/// Offsets are expressed in the length unit of the decoded characters (bytes
+/// by default).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Span {
+	/// Start offset (included).
+	pub start: usize,
+
+	/// End offset (excluded).
+	pub end: usize,
+}
+
+impl Span {
+	/// Creates a new span from its start (included) and end (excluded)
+	/// offsets.
+	#[inline(always)]
+	pub fn new(start: usize, end: usize) -> Self {
+		Self { start, end }
+	}
+
+	/// Creates an empty span at the given offset.
+	#[inline(always)]
+	pub fn empty(offset: usize) -> Self {
+		Self::new(offset, offset)
+	}
+
+	/// Returns the length of the span.
+	#[inline(always)]
+	pub fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	/// Checks if the span is empty.
+	#[inline(always)]
+	pub fn is_empty(&self) -> bool {
+		self.start == self.end
+	}
+
+	/// Checks if the span contains the given offset.
+	#[inline(always)]
+	pub fn contains(&self, offset: usize) -> bool {
+		self.start <= offset && offset < self.end
+	}
+
+	/// Returns the smallest span containing both `self` and `other`.
+	#[inline(always)]
+	pub fn union(&self, other: Self) -> Self {
+		Self::new(self.start.min(other.start), self.end.max(other.end))
+	}
+
+	/// Returns the span as a range of offsets.
+	#[inline(always)]
+	pub fn range(&self) -> Range<usize> {
+		self.start..self.end
+	}
+}
+
+impl From<Range<usize>> for Span {
+	#[inline(always)]
+	fn from(range: Range<usize>) -> Self {
+		Self::new(range.start, range.end)
+	}
+}
+
+impl From<Span> for Range<usize> {
+	#[inline(always)]
+	fn from(span: Span) -> Self {
+		span.range()
+	}
+}
+
+/// Span-accumulating cursor over decoded characters.
+///
+/// Keeps track of the offset of the next character and of the start of the
+/// current token, so that lexers can retrieve the span of each token.
+/// Works with both infallible and fallible iterators over decoded characters.
+///
+/// ```
+/// use decoded_char::{Cursor, DecodedChars, Span};
+///
+/// let mut cursor = Cursor::new("é1 x".decoded_chars());
+/// while cursor.peek().is_some_and(|c| !c.is_whitespace()) {
+///     cursor.next();
+/// }
+/// assert_eq!(cursor.take_span(), Span::new(0, 3));
+///
+/// cursor.next();
+/// cursor.begin_token();
+/// cursor.next();
+/// assert_eq!(cursor.take_span(), Span::new(4, 5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cursor<I: Iterator> {
+	/// Decoded characters.
+	iter: I,
+
+	/// Peeked item, if any.
+	peeked: Option<Option<I::Item>>,
+
+	/// Offset of the next character.
+	offset: usize,
+
+	/// Start offset of the current token.
+	token_start: usize,
+}
+
+impl<I: Iterator> Cursor<I>
+where
+	I::Item: DecodedItem,
+{
+	#[inline(always)]
+	pub fn new(iter: I) -> Self {
+		Self::with_start(iter, 0)
+	}
+
+	/// Creates a new cursor where the first character is at the given offset.
+	#[inline(always)]
+	pub fn with_start(iter: I, offset: usize) -> Self {
+		Self {
+			iter,
+			peeked: None,
+			offset,
+			token_start: offset,
+		}
+	}
+
+	/// Returns the offset of the next character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns a reference to the next item, without consuming it.
+	#[inline(always)]
+	pub fn peek(&mut self) -> Option<&I::Item> {
+		let iter = &mut self.iter;
+		self.peeked.get_or_insert_with(|| iter.next()).as_ref()
+	}
+
+	/// Consumes and returns the next item.
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Option<I::Item> {
+		let item = match self.peeked.take() {
+			Some(item) => item,
+			None => self.iter.next(),
+		};
+
+		if let Some(item) = &item {
+			self.offset += item.source_len().get();
+		}
+
+		item
+	}
+
+	/// Consumes the next item if it satisfies the given predicate.
+	pub fn next_if(&mut self, f: impl FnOnce(&I::Item) -> bool) -> Option<I::Item> {
+		match self.peek() {
+			Some(item) if f(item) => self.next(),
+			_ => None,
+		}
+	}
+
+	/// Starts a new token at the current offset, discarding the current one.
+	#[inline(always)]
+	pub fn begin_token(&mut self) {
+		self.token_start = self.offset
+	}
+
+	/// Returns the span of the current token, from its start to the current
+	/// offset.
+	#[inline(always)]
+	pub fn span(&self) -> Span {
+		Span::new(self.token_start, self.offset)
+	}
+
+	/// Returns the span of the current token and starts a new token.
+	#[inline(always)]
+	pub fn take_span(&mut self) -> Span {
+		let span = self.span();
+		self.begin_token();
+		span
+	}
+
+	/// Returns the underlying iterator.
+	///
+	/// Any peeked item is lost.
+	#[inline(always)]
+	pub fn into_inner(self) -> I {
+		self.iter
+	}
+}
+
+impl<I: Iterator> Iterator for Cursor<I>
+where
+	I::Item: DecodedItem,
+{
+	type Item = I::Item;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		Cursor::next(self)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Utf8BytesDecoded;
+
+	#[test]
+	fn span() {
+		let span = Span::new(2, 5);
+		assert_eq!(span.len(), 3);
+		assert!(span.contains(2) && span.contains(4) && !span.contains(5));
+		assert!(Span::empty(3).is_empty());
+		assert_eq!(span.union(Span::new(7, 8)), Span::new(2, 8));
+		assert_eq!(Span::from(1..4).range(), 1..4);
+	}
+
+	#[test]
+	fn cursor_over_errors() {
+		let mut cursor =
+			Cursor::with_start(Utf8BytesDecoded::new(b"ab\xe2\x82c".iter().copied()), 10);
+		assert!(cursor.next_if(|c| c.is_ok()).is_some());
+		assert_eq!(cursor.take_span(), Span::new(10, 11));
+
+		while cursor
+			.next_if(|c| c.as_ref().map_or(true, |c| **c != 'c'))
+			.is_some()
+		{}
+		assert_eq!(cursor.span(), Span::new(11, 14));
+
+		cursor.begin_token();
+		assert!(cursor.peek().is_some());
+		assert_eq!(cursor.offset(), 14);
+		cursor.next();
+		assert_eq!(cursor.take_span(), Span::new(14, 15));
+		assert!(cursor.next().is_none());
+		assert_eq!(cursor.span(), Span::empty(15));
+	}
+}
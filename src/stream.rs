@@ -0,0 +1,311 @@
+use crate::{DecodeError, DecodedChar, Utf8Stream};
+use futures_core::Stream;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Polls the next decoded character from an asynchronous buffered reader.
+///
+/// Works with both the `futures` and `tokio` `AsyncBufRead` traits, which
+/// share the same method names.
+macro_rules! poll_decode {
+	($reader:expr, $stream:expr, $cx:expr) => {
+		loop {
+			let buffer = match Pin::new(&mut $reader).poll_fill_buf($cx) {
+				Poll::Pending => break Poll::Pending,
+				Poll::Ready(Ok(buffer)) => buffer,
+				Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Poll::Ready(Err(error)) => {
+					break Poll::Ready(Some(Err(DecodeError::Io {
+						offset: $stream.position(),
+						error,
+					})))
+				}
+			};
+
+			if buffer.is_empty() {
+				break Poll::Ready($stream.finish().map(|e| Err(e.into())));
+			}
+
+			let (consumed, item) = $stream.decode(buffer);
+			Pin::new(&mut $reader).consume(consumed);
+
+			if let Some(item) = item {
+				break Poll::Ready(Some(item.map_err(Into::into)));
+			}
+		}
+	};
+}
+
+/// Asynchronous decoder for UTF-8 encoded `futures` readers.
+///
+/// Asynchronous counterpart of [`ReaderDecoded`](crate::ReaderDecoded),
+/// producing the same characters, lengths and offsets.
+#[cfg(feature = "futures")]
+#[derive(Debug)]
+pub struct AsyncReaderDecoded<R> {
+	/// Reader.
+	reader: R,
+
+	/// Decoding state.
+	stream: Utf8Stream,
+}
+
+#[cfg(feature = "futures")]
+impl<R> AsyncReaderDecoded<R> {
+	#[inline(always)]
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			stream: Utf8Stream::default(),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.stream.offset()
+	}
+
+	/// Returns a reference to the underlying reader.
+	#[inline(always)]
+	pub fn get_ref(&self) -> &R {
+		&self.reader
+	}
+
+	/// Returns the underlying reader.
+	#[inline(always)]
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+
+#[cfg(feature = "futures")]
+impl<R: futures_io::AsyncBufRead + Unpin> Stream for AsyncReaderDecoded<R> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+		poll_decode!(this.reader, this.stream, cx)
+	}
+}
+
+/// Asynchronous decoder for UTF-8 encoded `tokio` readers.
+///
+/// Asynchronous counterpart of [`ReaderDecoded`](crate::ReaderDecoded),
+/// producing the same characters, lengths and offsets.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioReaderDecoded<R> {
+	/// Reader.
+	reader: R,
+
+	/// Decoding state.
+	stream: Utf8Stream,
+}
+
+#[cfg(feature = "tokio")]
+impl<R> TokioReaderDecoded<R> {
+	#[inline(always)]
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			stream: Utf8Stream::default(),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.stream.offset()
+	}
+
+	/// Returns a reference to the underlying reader.
+	#[inline(always)]
+	pub fn get_ref(&self) -> &R {
+		&self.reader
+	}
+
+	/// Returns the underlying reader.
+	#[inline(always)]
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncBufRead + Unpin> Stream for TokioReaderDecoded<R> {
+	type Item = Result<DecodedChar, DecodeError>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+		poll_decode!(this.reader, this.stream, cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Reader yielding one byte at a time, and pending before each byte.
+	struct Trickle<'a> {
+		/// Remaining bytes.
+		bytes: &'a [u8],
+
+		/// Whether or not the next byte is ready.
+		ready: bool,
+	}
+
+	impl<'a> Trickle<'a> {
+		fn new(bytes: &'a [u8]) -> Self {
+			Self {
+				bytes,
+				ready: false,
+			}
+		}
+
+		fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+			if self.ready {
+				self.ready = false;
+				Poll::Ready(Ok(&self.bytes[..self.bytes.len().min(1)]))
+			} else {
+				self.ready = true;
+				cx.waker().wake_by_ref();
+				Poll::Pending
+			}
+		}
+
+		fn consume(&mut self, amt: usize) {
+			self.bytes = &self.bytes[amt..]
+		}
+
+		fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+			let len = match self.poll_fill_buf(cx) {
+				Poll::Ready(Ok(bytes)) => {
+					let len = bytes.len().min(buf.len());
+					buf[..len].copy_from_slice(&bytes[..len]);
+					len
+				}
+				Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+				Poll::Pending => return Poll::Pending,
+			};
+
+			self.consume(len);
+			Poll::Ready(Ok(len))
+		}
+	}
+
+	#[cfg(feature = "futures")]
+	impl futures_io::AsyncRead for Trickle<'_> {
+		fn poll_read(
+			self: Pin<&mut Self>,
+			cx: &mut Context<'_>,
+			buf: &mut [u8],
+		) -> Poll<io::Result<usize>> {
+			self.get_mut().poll_read(cx, buf)
+		}
+	}
+
+	#[cfg(feature = "futures")]
+	impl futures_io::AsyncBufRead for Trickle<'_> {
+		fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+			self.get_mut().poll_fill_buf(cx)
+		}
+
+		fn consume(self: Pin<&mut Self>, amt: usize) {
+			self.get_mut().consume(amt)
+		}
+	}
+
+	#[cfg(feature = "tokio")]
+	impl tokio::io::AsyncRead for Trickle<'_> {
+		fn poll_read(
+			self: Pin<&mut Self>,
+			cx: &mut Context<'_>,
+			buf: &mut tokio::io::ReadBuf<'_>,
+		) -> Poll<io::Result<()>> {
+			self.get_mut()
+				.poll_read(cx, buf.initialize_unfilled())
+				.map_ok(|len| buf.advance(len))
+		}
+	}
+
+	#[cfg(feature = "tokio")]
+	impl tokio::io::AsyncBufRead for Trickle<'_> {
+		fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+			self.get_mut().poll_fill_buf(cx)
+		}
+
+		fn consume(self: Pin<&mut Self>, amt: usize) {
+			self.get_mut().consume(amt)
+		}
+	}
+
+	/// Polls the given stream to completion, returning the characters with
+	/// their byte length, or the offset of errors.
+	fn collect<S>(mut stream: S) -> Vec<Result<(char, usize), usize>>
+	where
+		S: Stream<Item = Result<DecodedChar, DecodeError>> + Unpin,
+	{
+		let mut cx = Context::from_waker(std::task::Waker::noop());
+		let mut items = Vec::new();
+		loop {
+			match Pin::new(&mut stream).poll_next(&mut cx) {
+				Poll::Ready(Some(item)) => {
+					items.push(item.map(|c| (c.chr(), c.len().0)).map_err(|e| e.offset()))
+				}
+				Poll::Ready(None) => break items,
+				Poll::Pending => (),
+			}
+		}
+	}
+
+	const INPUT: &[u8] = b"a\xc3\xa9\xf0\x9f\x98\x80\xe2\x82b\xf0\x9f";
+
+	const EXPECTED: [Result<(char, usize), usize>; 6] = [
+		Ok(('a', 1)),
+		Ok(('é', 2)),
+		Ok(('😀', 4)),
+		Err(7),
+		Ok(('b', 1)),
+		Err(10),
+	];
+
+	#[test]
+	fn trickle_read() {
+		let mut cx = Context::from_waker(std::task::Waker::noop());
+		let mut reader = Trickle::new(INPUT);
+		let mut bytes = Vec::new();
+		let mut buf = [0; 4];
+		loop {
+			match reader.poll_read(&mut cx, &mut buf) {
+				Poll::Ready(Ok(0)) => break,
+				Poll::Ready(Ok(len)) => bytes.extend_from_slice(&buf[..len]),
+				Poll::Ready(Err(e)) => panic!("{e}"),
+				Poll::Pending => (),
+			}
+		}
+
+		assert_eq!(bytes, INPUT);
+	}
+
+	#[cfg(feature = "futures")]
+	#[test]
+	fn futures_reader() {
+		assert_eq!(
+			collect(AsyncReaderDecoded::new(Trickle::new(INPUT))),
+			EXPECTED
+		);
+	}
+
+	#[cfg(feature = "tokio")]
+	#[test]
+	fn tokio_reader() {
+		assert_eq!(
+			collect(TokioReaderDecoded::new(Trickle::new(INPUT))),
+			EXPECTED
+		);
+	}
+}
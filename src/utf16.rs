@@ -0,0 +1,464 @@
+use crate::{ByteLen, DecodedChar, Endianness, Length, Utf16Len};
+use std::marker::PhantomData;
+
+/// Iterator wrapper around UTF-16 encoded sources.
+///
+/// The length of each character is reported in the unit `L`, bytes by
+/// default. Use [`Utf16Decoded::with_code_units`] to report lengths in UTF-16
+/// code units instead.
+#[derive(Clone, Debug)]
+pub struct Utf16Decoded<C, L = ByteLen>(pub C, PhantomData<L>);
+
+impl<C> Utf16Decoded<C> {
+	/// Wraps the given characters, reporting lengths in bytes.
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars, PhantomData)
+	}
+}
+
+impl<C> Utf16Decoded<C, Utf16Len> {
+	/// Wraps the given characters, reporting lengths in UTF-16 code units.
+	#[inline(always)]
+	pub fn with_code_units(chars: C) -> Self {
+		Self(chars, PhantomData)
+	}
+}
+
+impl<C: Iterator<Item = char>, L: Length> Iterator for Utf16Decoded<C, L> {
+	type Item = DecodedChar<L>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|c| DecodedChar::new(c, L::of_utf16(c)))
+	}
+}
+
+impl<C: DoubleEndedIterator<Item = char>, L: Length> DoubleEndedIterator for Utf16Decoded<C, L> {
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|c| DecodedChar::new(c, L::of_utf16(c)))
+	}
+}
+
+/// Iterator wrapper around fallible UTF-16 encoded sources.
+///
+/// The length of each character is reported in the unit `L`, bytes by
+/// default. Use [`FallibleUtf16Decoded::with_code_units`] to report lengths in
+/// UTF-16 code units instead.
+#[derive(Clone, Debug)]
+pub struct FallibleUtf16Decoded<C, L = ByteLen>(pub C, PhantomData<L>);
+
+impl<C> FallibleUtf16Decoded<C> {
+	/// Wraps the given characters, reporting lengths in bytes.
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars, PhantomData)
+	}
+}
+
+impl<C> FallibleUtf16Decoded<C, Utf16Len> {
+	/// Wraps the given characters, reporting lengths in UTF-16 code units.
+	#[inline(always)]
+	pub fn with_code_units(chars: C) -> Self {
+		Self(chars, PhantomData)
+	}
+}
+
+impl<E, C: Iterator<Item = Result<char, E>>, L: Length> Iterator for FallibleUtf16Decoded<C, L> {
+	type Item = Result<DecodedChar<L>, E>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0
+			.next()
+			.map(|result| result.map(|c| DecodedChar::new(c, L::of_utf16(c))))
+	}
+}
+
+impl<E, C: DoubleEndedIterator<Item = Result<char, E>>, L: Length> DoubleEndedIterator
+	for FallibleUtf16Decoded<C, L>
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|result| result.map(|c| DecodedChar::new(c, L::of_utf16(c))))
+	}
+}
+
+/// UTF-16 decoding error.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Utf16Error {
+	/// Surrogate code unit that is not part of a surrogate pair.
+	UnpairedSurrogate {
+		/// Byte offset of the code unit in the encoded source file.
+		offset: usize,
+
+		/// Surrogate code unit.
+		unit: u16,
+	},
+
+	/// The input ended in the middle of a code unit.
+	Truncated {
+		/// Byte offset of the incomplete code unit in the encoded source file.
+		offset: usize,
+	},
+}
+
+impl Utf16Error {
+	/// Returns the byte offset of the invalid sequence in the encoded source
+	/// file.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::UnpairedSurrogate { offset, .. } => *offset,
+			Self::Truncated { offset } => *offset,
+		}
+	}
+
+	/// Returns the byte length of the invalid sequence.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		match self {
+			Self::UnpairedSurrogate { .. } => 2,
+			Self::Truncated { .. } => 1,
+		}
+	}
+}
+
+impl std::fmt::Display for Utf16Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::UnpairedSurrogate { offset, unit } => {
+				write!(f, "unpaired surrogate {unit:#06x} at offset {offset}")
+			}
+			Self::Truncated { offset } => {
+				write!(f, "incomplete UTF-16 code unit at offset {offset}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for Utf16Error {}
+
+/// Result of feeding a byte to a [`Utf16Machine`].
+pub(crate) enum Utf16Step {
+	/// The byte was consumed, but the sequence is not complete yet.
+	Pending,
+
+	/// A character has been decoded, with the given byte length.
+	Char(char, usize),
+
+	/// Unpaired surrogate code unit, 2 bytes long.
+	Unpaired(u16),
+
+	/// Incomplete code unit, 1 byte long.
+	Truncated,
+}
+
+/// UTF-16 decoding state machine.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Utf16Machine {
+	endianness: Endianness,
+
+	/// First byte of the current code unit.
+	byte: Option<u8>,
+
+	/// Leading surrogate waiting for its trailing surrogate.
+	lead: Option<u16>,
+
+	/// Code unit following an unpaired leading surrogate, still to be
+	/// processed.
+	deferred: Option<u16>,
+}
+
+impl Utf16Machine {
+	#[inline(always)]
+	pub fn new(endianness: Endianness) -> Self {
+		Self {
+			endianness,
+			byte: None,
+			lead: None,
+			deferred: None,
+		}
+	}
+
+	/// Feeds the next byte to the machine.
+	///
+	/// [`Self::pop`] must be called until it returns `None` before feeding a
+	/// new byte.
+	pub fn push(&mut self, b: u8) -> Utf16Step {
+		match self.byte.take() {
+			None => {
+				self.byte = Some(b);
+				Utf16Step::Pending
+			}
+			Some(first) => {
+				let unit = match self.endianness {
+					Endianness::Little => u16::from_le_bytes([first, b]),
+					Endianness::Big => u16::from_be_bytes([first, b]),
+				};
+
+				self.push_unit(unit)
+			}
+		}
+	}
+
+	fn push_unit(&mut self, unit: u16) -> Utf16Step {
+		match self.lead.take() {
+			Some(lead) => {
+				if (0xdc00..=0xdfff).contains(&unit) {
+					let code_point =
+						0x10000 + (((lead as u32) - 0xd800) << 10) + ((unit as u32) - 0xdc00);
+					Utf16Step::Char(char::from_u32(code_point).unwrap(), 4)
+				} else {
+					self.deferred = Some(unit);
+					Utf16Step::Unpaired(lead)
+				}
+			}
+			None => match unit {
+				0xd800..=0xdbff => {
+					self.lead = Some(unit);
+					Utf16Step::Pending
+				}
+				0xdc00..=0xdfff => Utf16Step::Unpaired(unit),
+				_ => Utf16Step::Char(char::from_u32(unit as u32).unwrap(), 2),
+			},
+		}
+	}
+
+	/// Processes the code unit that followed an unpaired surrogate, if any.
+	#[inline(always)]
+	pub fn pop(&mut self) -> Option<Utf16Step> {
+		self.deferred.take().map(|unit| self.push_unit(unit))
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Must be called until it returns `None`.
+	pub fn finish(&mut self) -> Option<Utf16Step> {
+		if let Some(lead) = self.lead.take() {
+			Some(Utf16Step::Unpaired(lead))
+		} else if self.byte.take().is_some() {
+			Some(Utf16Step::Truncated)
+		} else {
+			None
+		}
+	}
+}
+
+/// Decoder for UTF-16 encoded bytes.
+///
+/// Decodes characters directly from an iterator over the bytes of the encoded
+/// source file, with the given byte order. Surrogate pairs are combined into
+/// a single `DecodedChar` of length 4.
+#[derive(Clone, Debug)]
+pub struct Utf16BytesDecoded<B> {
+	/// Encoded bytes.
+	bytes: B,
+
+	/// Whether or not `bytes` has ended, after which it is not pulled again so
+	/// that a truncated code unit is never completed by later bytes.
+	ended: bool,
+
+	/// Decoding state.
+	machine: Utf16Machine,
+
+	/// Byte offset of the next sequence.
+	offset: usize,
+}
+
+impl<B> Utf16BytesDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, endianness: Endianness) -> Self {
+		Self::with_start(bytes, endianness, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	#[inline(always)]
+	pub fn with_start(bytes: B, endianness: Endianness, offset: usize) -> Self {
+		Self {
+			bytes,
+			ended: false,
+			machine: Utf16Machine::new(endianness),
+			offset,
+		}
+	}
+
+	/// Creates a decoder for UTF-16LE encoded bytes.
+	#[inline(always)]
+	pub fn le(bytes: B) -> Self {
+		Self::new(bytes, Endianness::Little)
+	}
+
+	/// Creates a decoder for UTF-16BE encoded bytes.
+	#[inline(always)]
+	pub fn be(bytes: B) -> Self {
+		Self::new(bytes, Endianness::Big)
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	fn emit(&mut self, step: Utf16Step) -> Option<Result<DecodedChar, Utf16Error>> {
+		let offset = self.offset;
+		match step {
+			Utf16Step::Pending => None,
+			Utf16Step::Char(c, len) => {
+				self.offset += len;
+				Some(Ok(DecodedChar::new(c, ByteLen(len))))
+			}
+			Utf16Step::Unpaired(unit) => {
+				self.offset += 2;
+				Some(Err(Utf16Error::UnpairedSurrogate { offset, unit }))
+			}
+			Utf16Step::Truncated => {
+				self.offset += 1;
+				Some(Err(Utf16Error::Truncated { offset }))
+			}
+		}
+	}
+}
+
+impl<B: Iterator<Item = u8>> Utf16BytesDecoded<B> {
+	/// Pulls the next byte, until the input ends.
+	#[inline(always)]
+	fn next_byte(&mut self) -> Option<u8> {
+		if self.ended {
+			return None;
+		}
+
+		let b = self.bytes.next();
+		self.ended = b.is_none();
+		b
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for Utf16BytesDecoded<B> {
+	type Item = Result<DecodedChar, Utf16Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let step = match self.machine.pop() {
+				Some(step) => step,
+				None => match self.next_byte() {
+					Some(b) => self.machine.push(b),
+					None => return self.machine.finish().and_then(|step| self.emit(step)),
+				},
+			};
+
+			if let Some(item) = self.emit(step) {
+				break Some(item);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Decoded character with its byte length, or error.
+	type Item = Result<(char, usize), Utf16Error>;
+
+	/// Decodes the given UTF-16 code units, encoded with the given byte order.
+	fn decode(units: &[u16], endianness: Endianness) -> Vec<Item> {
+		let bytes: Vec<u8> = units
+			.iter()
+			.flat_map(|u| match endianness {
+				Endianness::Little => u.to_le_bytes(),
+				Endianness::Big => u.to_be_bytes(),
+			})
+			.collect();
+
+		Utf16BytesDecoded::new(bytes.into_iter(), endianness)
+			.map(|item| item.map(|c| (c.chr(), c.len().0)))
+			.collect()
+	}
+
+	#[test]
+	fn surrogate_pairs() {
+		for endianness in [Endianness::Little, Endianness::Big] {
+			assert_eq!(
+				decode(&[0x61, 0xd83d, 0xde00, 0xe9], endianness),
+				[Ok(('a', 2)), Ok(('😀', 4)), Ok(('é', 2))]
+			);
+		}
+	}
+
+	#[test]
+	fn unpaired_surrogates() {
+		let e = |offset, unit| Err(Utf16Error::UnpairedSurrogate { offset, unit });
+
+		// Leading surrogate followed by a regular code unit.
+		assert_eq!(
+			decode(&[0xd83d, 0x61], Endianness::Little),
+			[e(0, 0xd83d), Ok(('a', 2))]
+		);
+
+		// Leading surrogate followed by another leading surrogate and a pair.
+		assert_eq!(
+			decode(&[0xd83d, 0xd83d, 0xde00], Endianness::Big),
+			[e(0, 0xd83d), Ok(('😀', 4))]
+		);
+
+		// Lone trailing surrogate.
+		assert_eq!(
+			decode(&[0xde00, 0x61], Endianness::Little),
+			[e(0, 0xde00), Ok(('a', 2))]
+		);
+
+		// Leading surrogate at the end of the input.
+		assert_eq!(
+			decode(&[0x61, 0xd83d], Endianness::Little),
+			[Ok(('a', 2)), e(2, 0xd83d)]
+		);
+	}
+
+	#[test]
+	fn truncated() {
+		let items: Vec<_> = Utf16BytesDecoded::le([0x61, 0x00, 0x62].into_iter()).collect();
+		assert_eq!(
+			items,
+			[
+				Ok(DecodedChar::new('a', ByteLen(2))),
+				Err(Utf16Error::Truncated { offset: 2 })
+			]
+		);
+	}
+
+	#[test]
+	fn unfused_input() {
+		// Bytes following the end of the input are never decoded.
+		let mut bytes = [Some(0x3d), Some(0xd8), Some(0x61), None, Some(0x00)].into_iter();
+		let items: Vec<_> =
+			Utf16BytesDecoded::le(std::iter::from_fn(|| bytes.next().flatten())).collect();
+		assert_eq!(
+			items,
+			[
+				Err(Utf16Error::UnpairedSurrogate {
+					offset: 0,
+					unit: 0xd83d
+				}),
+				Err(Utf16Error::Truncated { offset: 2 })
+			]
+		);
+	}
+
+	#[test]
+	fn code_units() {
+		let lens: Vec<_> = Utf16Decoded::with_code_units("a😀".chars())
+			.map(|c| c.len())
+			.collect();
+		assert_eq!(lens, [Utf16Len(1), Utf16Len(2)]);
+	}
+}
@@ -0,0 +1,358 @@
+use crate::{ByteLen, DecodedChar, Endianness};
+
+/// Iterator wrapper around UTF-32 encoded sources.
+#[derive(Clone, Debug)]
+pub struct Utf32Decoded<C>(pub C);
+
+impl<C> Utf32Decoded<C> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars)
+	}
+}
+
+impl<C: Iterator<Item = char>> Iterator for Utf32Decoded<C> {
+	type Item = DecodedChar;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(DecodedChar::from_utf32)
+	}
+}
+
+impl<C: DoubleEndedIterator<Item = char>> DoubleEndedIterator for Utf32Decoded<C> {
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0.next_back().map(DecodedChar::from_utf32)
+	}
+}
+
+/// Iterator wrapper around fallible UTF-32 encoded sources.
+#[derive(Clone, Debug)]
+pub struct FallibleUtf32Decoded<C>(pub C);
+
+impl<C> FallibleUtf32Decoded<C> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars)
+	}
+}
+
+impl<E, C: Iterator<Item = Result<char, E>>> Iterator for FallibleUtf32Decoded<C> {
+	type Item = Result<DecodedChar, E>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0
+			.next()
+			.map(|result| result.map(DecodedChar::from_utf32))
+	}
+}
+
+impl<E, C: DoubleEndedIterator<Item = Result<char, E>>> DoubleEndedIterator
+	for FallibleUtf32Decoded<C>
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|result| result.map(DecodedChar::from_utf32))
+	}
+}
+
+/// UTF-32 decoding error.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Utf32Error {
+	/// Code unit encoding a surrogate code point.
+	Surrogate {
+		/// Byte offset of the code unit in the encoded source file.
+		offset: usize,
+
+		/// Code unit value.
+		value: u32,
+	},
+
+	/// Code unit greater than `0x10FFFF`.
+	OutOfRange {
+		/// Byte offset of the code unit in the encoded source file.
+		offset: usize,
+
+		/// Code unit value.
+		value: u32,
+	},
+
+	/// The input ended in the middle of a code unit.
+	Truncated {
+		/// Byte offset of the incomplete code unit in the encoded source file.
+		offset: usize,
+
+		/// Byte length of the incomplete code unit (1 to 3).
+		len: usize,
+	},
+}
+
+impl Utf32Error {
+	/// Returns the byte offset of the invalid sequence in the encoded source
+	/// file.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		match self {
+			Self::Surrogate { offset, .. } => *offset,
+			Self::OutOfRange { offset, .. } => *offset,
+			Self::Truncated { offset, .. } => *offset,
+		}
+	}
+
+	/// Returns the byte length of the invalid sequence.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Surrogate { .. } | Self::OutOfRange { .. } => 4,
+			Self::Truncated { len, .. } => *len,
+		}
+	}
+}
+
+impl std::fmt::Display for Utf32Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Surrogate { offset, value } => {
+				write!(f, "surrogate code point {value:#x} at offset {offset}")
+			}
+			Self::OutOfRange { offset, value } => {
+				write!(f, "out of range code point {value:#x} at offset {offset}")
+			}
+			Self::Truncated { offset, len } => write!(
+				f,
+				"incomplete UTF-32 code unit of {len} byte(s) at offset {offset}"
+			),
+		}
+	}
+}
+
+impl std::error::Error for Utf32Error {}
+
+/// Result of feeding a byte to a [`Utf32Machine`].
+pub(crate) enum Utf32Step {
+	/// The byte was consumed, but the code unit is not complete yet.
+	Pending,
+
+	/// A character has been decoded.
+	Char(char),
+
+	/// The code unit does not encode a Unicode scalar value.
+	Invalid(u32),
+}
+
+/// UTF-32 decoding state machine.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Utf32Machine {
+	endianness: Endianness,
+
+	/// Bytes of the current code unit.
+	buffer: [u8; 4],
+
+	/// Number of bytes in `buffer`.
+	len: usize,
+}
+
+impl Utf32Machine {
+	#[inline(always)]
+	pub fn new(endianness: Endianness) -> Self {
+		Self {
+			endianness,
+			buffer: [0; 4],
+			len: 0,
+		}
+	}
+
+	/// Feeds the next byte to the machine.
+	pub fn push(&mut self, b: u8) -> Utf32Step {
+		self.buffer[self.len] = b;
+		self.len += 1;
+
+		if self.len == 4 {
+			self.len = 0;
+			let value = match self.endianness {
+				Endianness::Little => u32::from_le_bytes(self.buffer),
+				Endianness::Big => u32::from_be_bytes(self.buffer),
+			};
+
+			match char::from_u32(value) {
+				Some(c) => Utf32Step::Char(c),
+				None => Utf32Step::Invalid(value),
+			}
+		} else {
+			Utf32Step::Pending
+		}
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Returns the byte length of the truncated code unit, if any.
+	#[inline(always)]
+	pub fn finish(&mut self) -> Option<usize> {
+		let len = std::mem::take(&mut self.len);
+		if len > 0 {
+			Some(len)
+		} else {
+			None
+		}
+	}
+}
+
+/// Decoder for UTF-32 encoded bytes.
+///
+/// Decodes characters directly from an iterator over the bytes of the encoded
+/// source file, with the given byte order. Every decoded character has a
+/// length of 4 bytes.
+#[derive(Clone, Debug)]
+pub struct Utf32BytesDecoded<B> {
+	/// Encoded bytes.
+	bytes: B,
+
+	/// Whether or not `bytes` has ended, after which it is not pulled again so
+	/// that a truncated code unit is never completed by later bytes.
+	ended: bool,
+
+	/// Decoding state.
+	machine: Utf32Machine,
+
+	/// Byte offset of the next code unit.
+	offset: usize,
+}
+
+impl<B> Utf32BytesDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B, endianness: Endianness) -> Self {
+		Self::with_start(bytes, endianness, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	#[inline(always)]
+	pub fn with_start(bytes: B, endianness: Endianness, offset: usize) -> Self {
+		Self {
+			bytes,
+			ended: false,
+			machine: Utf32Machine::new(endianness),
+			offset,
+		}
+	}
+
+	/// Creates a decoder for UTF-32LE encoded bytes.
+	#[inline(always)]
+	pub fn le(bytes: B) -> Self {
+		Self::new(bytes, Endianness::Little)
+	}
+
+	/// Creates a decoder for UTF-32BE encoded bytes.
+	#[inline(always)]
+	pub fn be(bytes: B) -> Self {
+		Self::new(bytes, Endianness::Big)
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+
+impl<B: Iterator<Item = u8>> Utf32BytesDecoded<B> {
+	/// Pulls the next byte, until the input ends.
+	#[inline(always)]
+	fn next_byte(&mut self) -> Option<u8> {
+		if self.ended {
+			return None;
+		}
+
+		let b = self.bytes.next();
+		self.ended = b.is_none();
+		b
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for Utf32BytesDecoded<B> {
+	type Item = Result<DecodedChar, Utf32Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let offset = self.offset;
+			match self.next_byte() {
+				Some(b) => match self.machine.push(b) {
+					Utf32Step::Pending => (),
+					Utf32Step::Char(c) => {
+						self.offset += 4;
+						break Some(Ok(DecodedChar::new(c, ByteLen(4))));
+					}
+					Utf32Step::Invalid(value) => {
+						self.offset += 4;
+						break Some(Err(if (0xd800..=0xdfff).contains(&value) {
+							Utf32Error::Surrogate { offset, value }
+						} else {
+							Utf32Error::OutOfRange { offset, value }
+						}));
+					}
+				},
+				None => {
+					break self.machine.finish().map(|len| {
+						self.offset += len;
+						Err(Utf32Error::Truncated { offset, len })
+					})
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decode() {
+		let bytes = [0x61, 0, 0, 0, 0x00, 0xf6, 0x01, 0x00];
+		let chars: Vec<_> = Utf32BytesDecoded::le(bytes.into_iter())
+			.map(|c| c.map(|c| (c.chr(), c.len().0)))
+			.collect();
+		assert_eq!(chars, [Ok(('a', 4)), Ok(('😀', 4))]);
+
+		let bytes = [0, 0, 0, 0x61, 0x00, 0x01, 0xf6, 0x00];
+		let chars: Vec<_> = Utf32BytesDecoded::be(bytes.into_iter())
+			.map(|c| c.map(|c| (c.chr(), c.len().0)))
+			.collect();
+		assert_eq!(chars, [Ok(('a', 4)), Ok(('😀', 4))]);
+	}
+
+	#[test]
+	fn errors() {
+		let bytes = [0, 0xd8, 0, 0, 0, 0, 0x11, 0, 0x61, 0, 0];
+		let items: Vec<_> = Utf32BytesDecoded::le(bytes.into_iter()).collect();
+		assert_eq!(
+			items,
+			[
+				Err(Utf32Error::Surrogate {
+					offset: 0,
+					value: 0xd800
+				}),
+				Err(Utf32Error::OutOfRange {
+					offset: 4,
+					value: 0x110000
+				}),
+				Err(Utf32Error::Truncated { offset: 8, len: 3 })
+			]
+		);
+	}
+
+	#[test]
+	fn unfused_input() {
+		// Bytes following the end of the input are never decoded.
+		let mut bytes = [Some(0x61), Some(0), None, Some(0), Some(0)].into_iter();
+		let items: Vec<_> =
+			Utf32BytesDecoded::le(std::iter::from_fn(|| bytes.next().flatten())).collect();
+		assert_eq!(items, [Err(Utf32Error::Truncated { offset: 0, len: 2 })]);
+	}
+}
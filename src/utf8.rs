@@ -0,0 +1,867 @@
+use crate::{ByteLen, DecodedChar};
+
+/// Iterator wrapper around UTF-8 encoded sources.
+#[derive(Clone, Debug)]
+pub struct Utf8Decoded<C>(pub C);
+
+impl<C> Utf8Decoded<C> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars)
+	}
+}
+
+impl<C: Iterator<Item = char>> Iterator for Utf8Decoded<C> {
+	type Item = DecodedChar;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(DecodedChar::from_utf8)
+	}
+}
+
+impl<C: DoubleEndedIterator<Item = char>> DoubleEndedIterator for Utf8Decoded<C> {
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0.next_back().map(DecodedChar::from_utf8)
+	}
+}
+
+/// Iterator wrapper around fallible UTF-8 encoded sources.
+#[derive(Clone, Debug)]
+pub struct FallibleUtf8Decoded<C>(pub C);
+
+impl<C> FallibleUtf8Decoded<C> {
+	#[inline(always)]
+	pub fn new(chars: C) -> Self {
+		Self(chars)
+	}
+}
+
+impl<E, C: Iterator<Item = Result<char, E>>> Iterator for FallibleUtf8Decoded<C> {
+	type Item = Result<DecodedChar, E>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0
+			.next()
+			.map(|result| result.map(DecodedChar::from_utf8))
+	}
+}
+
+impl<E, C: DoubleEndedIterator<Item = Result<char, E>>> DoubleEndedIterator
+	for FallibleUtf8Decoded<C>
+{
+	#[inline(always)]
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|result| result.map(DecodedChar::from_utf8))
+	}
+}
+
+/// UTF-8 decoding error.
+///
+/// Describes an invalid (or truncated) UTF-8 sequence found in the encoded
+/// source file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Utf8Error {
+	/// Byte offset of the invalid sequence in the encoded source file.
+	offset: usize,
+
+	/// Byte length of the invalid sequence.
+	len: usize,
+
+	/// Whether or not the sequence is invalid because the input ended.
+	truncated: bool,
+}
+
+impl Utf8Error {
+	/// Returns the byte offset of the invalid sequence in the encoded source
+	/// file.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the byte length of the invalid sequence.
+	///
+	/// This is the length of the maximal subpart of a well-formed sequence,
+	/// as defined by the WHATWG Encoding Standard, and is never zero.
+	#[inline(always)]
+	#[allow(clippy::len_without_is_empty)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Checks if the sequence is invalid only because the input ended before
+	/// it was complete.
+	#[inline(always)]
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+}
+
+impl std::fmt::Display for Utf8Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		if self.truncated {
+			write!(
+				f,
+				"incomplete UTF-8 sequence of {} byte(s) at offset {}",
+				self.len, self.offset
+			)
+		} else {
+			write!(
+				f,
+				"invalid UTF-8 sequence of {} byte(s) at offset {}",
+				self.len, self.offset
+			)
+		}
+	}
+}
+
+impl std::error::Error for Utf8Error {}
+
+/// Result of feeding a byte to a [`Utf8Machine`].
+pub(crate) enum Utf8Step {
+	/// The byte was consumed, but the sequence is not complete yet.
+	Pending,
+
+	/// A character has been decoded, with the given byte length.
+	Char(char, usize),
+
+	/// The current sequence is invalid and has the given byte length.
+	///
+	/// If `reconsume` is set, the byte that triggered the error is not part
+	/// of the invalid sequence and must be fed again to the machine.
+	Invalid { len: usize, reconsume: bool },
+}
+
+/// UTF-8 decoding state machine.
+///
+/// Follows the UTF-8 decoder of the WHATWG Encoding Standard, so that invalid
+/// sequences are reported using their maximal subpart.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Utf8Machine {
+	code_point: u32,
+	bytes_seen: u8,
+	bytes_needed: u8,
+	lower_boundary: u8,
+	upper_boundary: u8,
+}
+
+impl Default for Utf8Machine {
+	#[inline(always)]
+	fn default() -> Self {
+		Self {
+			code_point: 0,
+			bytes_seen: 0,
+			bytes_needed: 0,
+			lower_boundary: 0x80,
+			upper_boundary: 0xbf,
+		}
+	}
+}
+
+impl Utf8Machine {
+	/// Checks if the machine is in the middle of a sequence.
+	#[inline(always)]
+	pub fn is_pending(&self) -> bool {
+		self.bytes_needed != 0
+	}
+
+	/// Feeds the next byte to the machine.
+	pub fn push(&mut self, b: u8) -> Utf8Step {
+		if self.bytes_needed == 0 {
+			match b {
+				0x00..=0x7f => Utf8Step::Char(b as char, 1),
+				0xc2..=0xdf => {
+					self.bytes_needed = 1;
+					self.code_point = (b & 0x1f) as u32;
+					self.bytes_seen = 1;
+					Utf8Step::Pending
+				}
+				0xe0..=0xef => {
+					match b {
+						0xe0 => self.lower_boundary = 0xa0,
+						0xed => self.upper_boundary = 0x9f,
+						_ => (),
+					}
+					self.bytes_needed = 2;
+					self.code_point = (b & 0x0f) as u32;
+					self.bytes_seen = 1;
+					Utf8Step::Pending
+				}
+				0xf0..=0xf4 => {
+					match b {
+						0xf0 => self.lower_boundary = 0x90,
+						0xf4 => self.upper_boundary = 0x8f,
+						_ => (),
+					}
+					self.bytes_needed = 3;
+					self.code_point = (b & 0x07) as u32;
+					self.bytes_seen = 1;
+					Utf8Step::Pending
+				}
+				_ => Utf8Step::Invalid {
+					len: 1,
+					reconsume: false,
+				},
+			}
+		} else if b < self.lower_boundary || b > self.upper_boundary {
+			let len = self.bytes_seen as usize;
+			*self = Self::default();
+			Utf8Step::Invalid {
+				len,
+				reconsume: true,
+			}
+		} else {
+			self.lower_boundary = 0x80;
+			self.upper_boundary = 0xbf;
+			self.code_point = (self.code_point << 6) | (b & 0x3f) as u32;
+			self.bytes_seen += 1;
+
+			if self.bytes_seen > self.bytes_needed {
+				let len = self.bytes_seen as usize;
+				let c = char::from_u32(self.code_point).expect("invalid code point");
+				*self = Self::default();
+				Utf8Step::Char(c, len)
+			} else {
+				Utf8Step::Pending
+			}
+		}
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Returns the byte length of the truncated sequence, if any.
+	pub fn finish(&mut self) -> Option<usize> {
+		if self.is_pending() {
+			let len = self.bytes_seen as usize;
+			*self = Self::default();
+			Some(len)
+		} else {
+			None
+		}
+	}
+}
+
+/// UTF-8 decoder operating on successive byte buffers.
+///
+/// Keeps track of partial sequences across buffers and of the byte offset of
+/// each sequence. This is the state shared by the buffered and streaming
+/// decoders.
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Utf8Stream {
+	/// Decoding state.
+	machine: Utf8Machine,
+
+	/// Byte offset of the current sequence.
+	offset: usize,
+}
+
+impl Utf8Stream {
+	/// Returns the byte offset of the current sequence.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Returns the number of bytes consumed so far.
+	#[inline(always)]
+	pub fn position(&self) -> usize {
+		self.offset + self.machine.bytes_seen as usize
+	}
+
+	/// Decodes the next sequence from the given buffer.
+	///
+	/// Returns the number of bytes consumed from the buffer, and the decoded
+	/// item if the sequence is complete. If no item is returned, the whole
+	/// buffer has been consumed.
+	pub fn decode(&mut self, buffer: &[u8]) -> (usize, Option<Result<DecodedChar, Utf8Error>>) {
+		for (i, &b) in buffer.iter().enumerate() {
+			match self.machine.push(b) {
+				Utf8Step::Pending => (),
+				Utf8Step::Char(c, len) => {
+					self.offset += len;
+					return (i + 1, Some(Ok(DecodedChar::new(c, ByteLen(len)))));
+				}
+				Utf8Step::Invalid { len, reconsume } => {
+					let offset = self.offset;
+					self.offset += len;
+					let consumed = if reconsume { i } else { i + 1 };
+					return (
+						consumed,
+						Some(Err(Utf8Error {
+							offset,
+							len,
+							truncated: false,
+						})),
+					);
+				}
+			}
+		}
+
+		(buffer.len(), None)
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Returns an error if the input ends with an incomplete sequence.
+	pub fn finish(&mut self) -> Option<Utf8Error> {
+		self.machine.finish().map(|len| {
+			let offset = self.offset;
+			self.offset += len;
+			Utf8Error {
+				offset,
+				len,
+				truncated: true,
+			}
+		})
+	}
+}
+
+/// Decoder for UTF-8 encoded bytes.
+///
+/// Decodes characters directly from an iterator over the bytes of the encoded
+/// source file, validating the input on the fly.
+#[derive(Clone, Debug)]
+pub struct Utf8BytesDecoded<B> {
+	/// Encoded bytes.
+	bytes: B,
+
+	/// Byte that must be processed before pulling from `bytes`.
+	pending: Option<u8>,
+
+	/// Decoding state.
+	machine: Utf8Machine,
+
+	/// Byte offset of the next sequence.
+	offset: usize,
+}
+
+impl<B> Utf8BytesDecoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B) -> Self {
+		Self::with_start(bytes, 0)
+	}
+
+	/// Creates a new decoder where the first byte is at the given offset in
+	/// the encoded source file.
+	#[inline(always)]
+	pub fn with_start(bytes: B, offset: usize) -> Self {
+		Self {
+			bytes,
+			pending: None,
+			machine: Utf8Machine::default(),
+			offset,
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	/// Turns this decoder into a lossy decoder, replacing invalid sequences
+	/// with `U+FFFD REPLACEMENT CHARACTER`.
+	#[inline(always)]
+	pub fn lossy(self) -> LossyUtf8Decoded<B> {
+		LossyUtf8Decoded(self)
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for Utf8BytesDecoded<B> {
+	type Item = Result<DecodedChar, Utf8Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.pending.take().or_else(|| self.bytes.next()) {
+				Some(b) => match self.machine.push(b) {
+					Utf8Step::Pending => (),
+					Utf8Step::Char(c, len) => {
+						self.offset += len;
+						break Some(Ok(DecodedChar::new(c, ByteLen(len))));
+					}
+					Utf8Step::Invalid { len, reconsume } => {
+						if reconsume {
+							self.pending = Some(b)
+						}
+
+						let offset = self.offset;
+						self.offset += len;
+						break Some(Err(Utf8Error {
+							offset,
+							len,
+							truncated: false,
+						}));
+					}
+				},
+				None => {
+					break self.machine.finish().map(|len| {
+						let offset = self.offset;
+						self.offset += len;
+						Err(Utf8Error {
+							offset,
+							len,
+							truncated: true,
+						})
+					})
+				}
+			}
+		}
+	}
+}
+
+/// Lossy decoder for UTF-8 encoded bytes.
+///
+/// Behaves like [`Utf8BytesDecoded`], but replaces each invalid sequence with
+/// a `U+FFFD REPLACEMENT CHARACTER`, in the manner of
+/// [`String::from_utf8_lossy`]. The replacement character carries the byte
+/// length of the sequence it replaces (its maximal subpart, as defined by the
+/// WHATWG Encoding Standard), so that offsets computed from the decoded
+/// characters stay correct.
+#[derive(Clone, Debug)]
+pub struct LossyUtf8Decoded<B>(Utf8BytesDecoded<B>);
+
+impl<B> LossyUtf8Decoded<B> {
+	#[inline(always)]
+	pub fn new(bytes: B) -> Self {
+		Self(Utf8BytesDecoded::new(bytes))
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.0.offset()
+	}
+}
+
+impl<B: Iterator<Item = u8>> Iterator for LossyUtf8Decoded<B> {
+	type Item = DecodedChar;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|result| {
+			result
+				.unwrap_or_else(|e| DecodedChar::new(char::REPLACEMENT_CHARACTER, ByteLen(e.len())))
+		})
+	}
+}
+
+/// Decoder for UTF-8 encoded byte slices.
+///
+/// Behaves like [`Utf8BytesDecoded`], but can also decode characters from the
+/// end of the slice. Sequences decoded from the end are identical to the ones
+/// decoded from the start: invalid sequences are reported using the same
+/// maximal subparts, with the same offsets.
+#[derive(Clone, Debug)]
+pub struct Utf8SliceDecoded<'a> {
+	/// Encoded bytes.
+	bytes: &'a [u8],
+
+	/// Byte offset of the next sequence, from the start.
+	start: usize,
+
+	/// Byte offset of the end of the next sequence, from the end.
+	end: usize,
+}
+
+impl<'a> Utf8SliceDecoded<'a> {
+	#[inline(always)]
+	pub fn new(bytes: &'a [u8]) -> Self {
+		Self {
+			bytes,
+			start: 0,
+			end: bytes.len(),
+		}
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character from the start.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.start
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the end of the
+	/// next character from the end.
+	#[inline(always)]
+	pub fn end_offset(&self) -> usize {
+		self.end
+	}
+
+	/// Returns the bytes remaining to be decoded.
+	#[inline(always)]
+	pub fn as_bytes(&self) -> &'a [u8] {
+		&self.bytes[self.start..self.end]
+	}
+
+	/// Decodes the sequence starting at the given offset.
+	///
+	/// Bytes past `self.end` may be read to find the end of the sequence,
+	/// but since `self.end` is always at a sequence boundary, the sequence
+	/// never extends past it.
+	fn decode_at(&self, offset: usize) -> Result<DecodedChar, Utf8Error> {
+		let mut machine = Utf8Machine::default();
+		for &b in &self.bytes[offset..] {
+			match machine.push(b) {
+				Utf8Step::Pending => (),
+				Utf8Step::Char(c, len) => return Ok(DecodedChar::new(c, ByteLen(len))),
+				Utf8Step::Invalid { len, .. } => {
+					return Err(Utf8Error {
+						offset,
+						len,
+						truncated: false,
+					})
+				}
+			}
+		}
+
+		Err(Utf8Error {
+			offset,
+			len: machine.finish().unwrap(),
+			truncated: true,
+		})
+	}
+}
+
+/// Returns the byte length of the given decoded item.
+#[inline(always)]
+fn item_len(item: &Result<DecodedChar, Utf8Error>) -> usize {
+	match item {
+		Ok(c) => c.len().0,
+		Err(e) => e.len,
+	}
+}
+
+impl<'a> Iterator for Utf8SliceDecoded<'a> {
+	type Item = Result<DecodedChar, Utf8Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.start < self.end {
+			let item = self.decode_at(self.start);
+			self.start += item_len(&item);
+			Some(item)
+		} else {
+			None
+		}
+	}
+}
+
+impl<'a> DoubleEndedIterator for Utf8SliceDecoded<'a> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.start < self.end {
+			// Every non-continuation byte starts a new sequence, and a sequence
+			// is at most 4 bytes long. Decoding from the last non-continuation
+			// byte in the last 4 bytes hence gives the same sequences as
+			// decoding from the start. If there is no such byte, the last byte
+			// is an invalid sequence on its own.
+			let window_start = self.start.max(self.end.saturating_sub(4));
+			let mut offset = (window_start..self.end)
+				.rev()
+				.find(|&i| self.bytes[i] & 0xc0 != 0x80)
+				.unwrap_or(self.end - 1);
+
+			loop {
+				let item = self.decode_at(offset);
+				offset += item_len(&item);
+				if offset == self.end {
+					self.end -= item_len(&item);
+					break Some(item);
+				}
+			}
+		} else {
+			None
+		}
+	}
+}
+
+/// Incremental UTF-8 decoder for chunked input.
+///
+/// Bytes are pushed to the decoder chunk by chunk with [`Utf8Decoder::feed`].
+/// Sequences split across chunks are decoded as if the input was contiguous,
+/// and offsets are counted from the start of the first chunk.
+///
+/// ```
+/// use decoded_char::Utf8Decoder;
+///
+/// let mut decoder = Utf8Decoder::new();
+/// let a: String = decoder.feed(b"caf\xc3").map(|c| *c.unwrap()).collect();
+/// let b: String = decoder.feed(b"\xa9!").map(|c| *c.unwrap()).collect();
+/// assert_eq!(a, "caf");
+/// assert_eq!(b, "é!");
+/// assert!(decoder.finish().is_ok());
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Utf8Decoder {
+	/// Decoding state.
+	stream: Utf8Stream,
+}
+
+impl Utf8Decoder {
+	#[inline(always)]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the byte offset, in the encoded source file, of the next
+	/// character.
+	#[inline(always)]
+	pub fn offset(&self) -> usize {
+		self.stream.offset()
+	}
+
+	/// Checks if the decoder is in the middle of a sequence, waiting for the
+	/// next chunk.
+	#[inline(always)]
+	pub fn is_pending(&self) -> bool {
+		self.stream.position() > self.stream.offset()
+	}
+
+	/// Feeds the next chunk to the decoder.
+	///
+	/// Returns an iterator over the characters completed by this chunk.
+	/// A trailing incomplete sequence is kept until the next chunk.
+	///
+	/// Dropping the iterator before its end decodes and discards the rest of
+	/// the chunk, so that the decoder stays consistent.
+	#[inline(always)]
+	pub fn feed<'a>(&'a mut self, chunk: &'a [u8]) -> Utf8Chunk<'a> {
+		Utf8Chunk {
+			stream: &mut self.stream,
+			chunk,
+		}
+	}
+
+	/// Signals the end of the input.
+	///
+	/// Returns an error if the input ends with an incomplete sequence. The
+	/// decoder can then be used again, with offsets continuing from the end of
+	/// the previous input.
+	#[inline(always)]
+	pub fn finish(&mut self) -> Result<(), Utf8Error> {
+		match self.stream.finish() {
+			Some(e) => Err(e),
+			None => Ok(()),
+		}
+	}
+}
+
+/// Iterator over the characters decoded from a chunk.
+///
+/// See [`Utf8Decoder::feed`].
+#[derive(Debug)]
+pub struct Utf8Chunk<'a> {
+	/// Decoding state.
+	stream: &'a mut Utf8Stream,
+
+	/// Remaining bytes of the chunk.
+	chunk: &'a [u8],
+}
+
+impl<'a> Iterator for Utf8Chunk<'a> {
+	type Item = Result<DecodedChar, Utf8Error>;
+
+	#[inline(always)]
+	fn next(&mut self) -> Option<Self::Item> {
+		let (consumed, item) = self.stream.decode(self.chunk);
+		self.chunk = &self.chunk[consumed..];
+		item
+	}
+}
+
+impl<'a> Drop for Utf8Chunk<'a> {
+	fn drop(&mut self) {
+		self.for_each(drop)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Decoded character with its byte length, or error with its offset,
+	/// length and truncation flag.
+	type Item = Result<(char, usize), (usize, usize, bool)>;
+
+	/// Decodes the given bytes.
+	fn decode(bytes: &[u8]) -> Vec<Item> {
+		Utf8BytesDecoded::new(bytes.iter().copied())
+			.map(|item| {
+				item.map(|c| (c.chr(), c.len().0))
+					.map_err(|e| (e.offset(), e.len(), e.is_truncated()))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn valid_sequences() {
+		assert_eq!(
+			decode("aé€😀".as_bytes()),
+			[Ok(('a', 1)), Ok(('é', 2)), Ok(('€', 3)), Ok(('😀', 4))]
+		);
+	}
+
+	#[test]
+	fn maximal_subparts() {
+		// Truncated sequence followed by an ASCII character.
+		assert_eq!(decode(b"\xe2\x82A"), [Err((0, 2, false)), Ok(('A', 1))]);
+
+		// Overlong encodings: the lead byte is invalid on its own.
+		assert_eq!(
+			decode(b"\xc0\xaf"),
+			[Err((0, 1, false)), Err((1, 1, false))]
+		);
+		assert_eq!(
+			decode(b"\xe0\x80\xaf"),
+			[Err((0, 1, false)), Err((1, 1, false)), Err((2, 1, false))]
+		);
+		assert_eq!(
+			decode(b"\xf0\x80\x80"),
+			[Err((0, 1, false)), Err((1, 1, false)), Err((2, 1, false))]
+		);
+
+		// Surrogates and code points above U+10FFFF.
+		assert_eq!(
+			decode(b"\xed\xa0\x80"),
+			[Err((0, 1, false)), Err((1, 1, false)), Err((2, 1, false))]
+		);
+		assert_eq!(
+			decode(b"\xf4\x90"),
+			[Err((0, 1, false)), Err((1, 1, false))]
+		);
+
+		// Invalid lead bytes.
+		assert_eq!(
+			decode(b"\xff\x80"),
+			[Err((0, 1, false)), Err((1, 1, false))]
+		);
+
+		// Valid prefix interrupted by a new sequence.
+		assert_eq!(
+			decode(b"\xf0\x9f\x98\xe2\x82\xac"),
+			[Err((0, 3, false)), Ok(('€', 3))]
+		);
+	}
+
+	#[test]
+	fn truncated_end() {
+		assert_eq!(decode(b"a\xe2\x82"), [Ok(('a', 1)), Err((1, 2, true))]);
+		assert_eq!(decode(b"\xf0\x9f\x98"), [Err((0, 3, true))]);
+	}
+
+	#[test]
+	fn start_offset() {
+		let mut decoder = Utf8BytesDecoded::with_start(b"a\xffb".iter().copied(), 10);
+		assert!(decoder.next().unwrap().is_ok());
+		assert_eq!(decoder.next().unwrap().unwrap_err().offset(), 11);
+		assert!(decoder.next().unwrap().is_ok());
+		assert_eq!(decoder.offset(), 13);
+	}
+
+	#[test]
+	fn lossy() {
+		let bytes = b"a\xe2\x82b\xff\xf0\x9f\x98";
+		let chars: Vec<_> = LossyUtf8Decoded::new(bytes.iter().copied())
+			.map(|c| (c.chr(), c.len().0))
+			.collect();
+		assert_eq!(
+			chars,
+			[
+				('a', 1),
+				('\u{fffd}', 2),
+				('b', 1),
+				('\u{fffd}', 1),
+				('\u{fffd}', 3)
+			]
+		);
+
+		let total: usize = chars.iter().map(|(_, len)| len).sum();
+		assert_eq!(total, bytes.len());
+
+		let lossy: String = chars.iter().map(|(c, _)| c).collect();
+		assert_eq!(lossy, String::from_utf8_lossy(bytes));
+	}
+
+	#[test]
+	fn slice_from_both_ends() {
+		const BYTES: [u8; 10] = [0x61, 0x80, 0xbf, 0xc3, 0xa9, 0xe2, 0xed, 0xf0, 0x9f, 0xff];
+
+		let mut inputs = vec![Vec::new()];
+		for _ in 0..4 {
+			inputs = inputs
+				.into_iter()
+				.flat_map(|input| {
+					BYTES.iter().map(move |&b| {
+						let mut input = input.clone();
+						input.push(b);
+						input
+					})
+				})
+				.collect();
+
+			for input in &inputs {
+				let forward: Vec<_> = Utf8SliceDecoded::new(input).collect();
+				let mut backward: Vec<_> = Utf8SliceDecoded::new(input).rev().collect();
+				backward.reverse();
+				assert_eq!(forward, backward, "{input:x?}");
+				assert_eq!(
+					forward,
+					Utf8BytesDecoded::new(input.iter().copied()).collect::<Vec<_>>()
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn slice_interleaved() {
+		let mut decoder = Utf8SliceDecoded::new("aé€😀".as_bytes());
+		assert_eq!(decoder.next_back().unwrap().unwrap().chr(), '😀');
+		assert_eq!(decoder.next().unwrap().unwrap().chr(), 'a');
+		assert_eq!(decoder.as_bytes(), "é€".as_bytes());
+		assert_eq!((decoder.offset(), decoder.end_offset()), (1, 6));
+		assert_eq!(decoder.next_back().unwrap().unwrap().chr(), '€');
+		assert_eq!(decoder.next_back().unwrap().unwrap().chr(), 'é');
+		assert!(decoder.next().is_none());
+	}
+
+	#[test]
+	fn chunk_splits() {
+		let input = b"a\xc3\xa9\xf0\x9f\x98\x80\xe2\x82b\xff\xf0\x9f";
+		let expected: Vec<_> = Utf8BytesDecoded::new(input.iter().copied()).collect();
+
+		for i in 0..=input.len() {
+			for j in i..=input.len() {
+				let mut decoder = Utf8Decoder::new();
+				let mut items: Vec<_> = decoder.feed(&input[..i]).collect();
+				items.extend(decoder.feed(&input[i..j]));
+				items.extend(decoder.feed(&input[j..]));
+				items.extend(decoder.finish().err().map(Err));
+				assert_eq!(items, expected, "split at {i} and {j}");
+				assert_eq!(decoder.offset(), input.len());
+			}
+		}
+	}
+
+	#[test]
+	fn chunk_pending() {
+		let mut decoder = Utf8Decoder::new();
+		assert_eq!(decoder.feed(b"\xf0\x9f").count(), 0);
+		assert!(decoder.is_pending());
+		assert_eq!(decoder.offset(), 0);
+
+		// Dropping the chunk iterator early still consumes the chunk.
+		drop(decoder.feed(b"\x98\x80ab"));
+		assert!(!decoder.is_pending());
+		assert_eq!(decoder.offset(), 6);
+
+		decoder.feed(b"\xe2").for_each(drop);
+		let e = decoder.finish().unwrap_err();
+		assert!(e.is_truncated());
+		assert_eq!((e.offset(), e.len()), (6, 1));
+	}
+}